@@ -9,8 +9,10 @@ use syn::{
     spanned::Spanned,
 };
 #[cfg(feature = "mdbx")]
+use quote::format_ident;
+#[cfg(feature = "mdbx")]
 use syn::{
-    Ident, Token, Type,
+    Ident, Meta, Token, Type,
     parse::{Parse, ParseStream},
     punctuated::Punctuated,
 };
@@ -216,6 +218,10 @@ pub fn derive_bcs_object(input: TokenStream) -> TokenStream {
             fn table_encode(&self) -> Result<Vec<u8>, mdbx_derive::Error> {
                 Ok(mdbx_derive::bcs::to_bytes(&self)?)
             }
+
+            fn codec_id() -> std::borrow::Cow<'static, str> {
+                std::borrow::Cow::Borrowed("Bcs")
+            }
         }
     };
     output.into()
@@ -260,6 +266,10 @@ pub fn derive_zstd_bcs_object(input: TokenStream) -> TokenStream {
                 })?;
                 Ok(compressed)
             }
+
+            fn codec_id() -> std::borrow::Cow<'static, str> {
+                std::borrow::Cow::Borrowed("Zstd<Bcs>")
+            }
         }
     };
     output.into()
@@ -280,6 +290,14 @@ pub fn derive_key_table_object(input: TokenStream) -> TokenStream {
             fn table_encode(&self) -> Result<Vec<u8>, mdbx_derive::Error> {
                 <#ident as mdbx_derive::KeyObjectEncode>::key_encode(self)
             }
+
+            fn codec_id() -> std::borrow::Cow<'static, str> {
+                std::borrow::Cow::Borrowed("Raw")
+            }
+        }
+
+        impl mdbx_derive::FixedSizeObject for #ident {
+            const FIXED_SIZE: usize = <#ident as mdbx_derive::KeyObjectDecode>::KEYSIZE;
         }
     };
     output.into()
@@ -294,10 +312,8 @@ pub fn derive_zstd_postcard(input: TokenStream) -> TokenStream {
     let table_object_impl = quote! {
         impl mdbx_derive::mdbx::TableObject for #ident {
             fn decode(data_val: &[u8]) -> Result<Self, mdbx_derive::mdbx::Error> {
-                let decompressed = mdbx_derive::zstd::decode_all(data_val).map_err(|_| {
-                    mdbx_derive::mdbx::Error::Corrupted
-                })?;
-                mdbx_derive::postcard::from_bytes(&decompressed).map_err(|_| mdbx_derive::mdbx::Error::Corrupted)
+                <Self as mdbx_derive::TableObjectDecode>::table_decode(data_val)
+                    .map_err(|_| mdbx_derive::mdbx::Error::Corrupted)
             }
         }
     };
@@ -310,7 +326,8 @@ pub fn derive_zstd_postcard(input: TokenStream) -> TokenStream {
                 let decompressed = mdbx_derive::zstd::decode_all(data_val).map_err(|e| {
                     mdbx_derive::Error::Zstd(e)
                 })?;
-                Ok(mdbx_derive::postcard::from_bytes(&decompressed)?)
+                // As strict as `Zstd<Postcard<Self>>`, which has the same `codec_id`
+                Ok(<mdbx_derive::Postcard<Self> as mdbx_derive::TableObjectDecode>::table_decode(&decompressed)?.into_inner())
             }
         }
 
@@ -324,6 +341,10 @@ pub fn derive_zstd_postcard(input: TokenStream) -> TokenStream {
                 })?;
                 Ok(compressed)
             }
+
+            fn codec_id() -> std::borrow::Cow<'static, str> {
+                std::borrow::Cow::Borrowed("Zstd<Postcard>")
+            }
         }
     };
     output.into()
@@ -369,6 +390,10 @@ pub fn derive_zstd_json(input: TokenStream) -> TokenStream {
                 })?;
                 Ok(compressed)
             }
+
+            fn codec_id() -> std::borrow::Cow<'static, str> {
+                std::borrow::Cow::Borrowed("Zstd<Json>")
+            }
         }
     };
     output.into()
@@ -378,7 +403,7 @@ pub fn derive_zstd_json(input: TokenStream) -> TokenStream {
 struct MacroInput {
     struct_name: Ident,
     error_type: Type,
-    tables: Punctuated<Type, Token![,]>,
+    tables: Vec<DbiTable>,
 }
 
 #[cfg(feature = "mdbx")]
@@ -388,7 +413,7 @@ impl Parse for MacroInput {
         input.parse::<Token![,]>()?;
         let error_type: Type = input.parse()?;
         input.parse::<Token![,]>()?;
-        let tables = input.parse_terminated(Type::parse, Token![,])?;
+        let tables = parse_tables(input)?;
         Ok(MacroInput {
             struct_name,
             error_type,
@@ -397,6 +422,111 @@ impl Parse for MacroInput {
     }
 }
 
+#[cfg(feature = "mdbx")]
+struct DatabaseInput {
+    db_name: Ident,
+    error_type: Type,
+    metadata_type: Type,
+    tables: Vec<DbiTable>,
+    migrations: Option<syn::Expr>,
+}
+
+#[cfg(feature = "mdbx")]
+impl Parse for DatabaseInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let db_name: Ident = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let error_type: Type = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let metadata_type: Type = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let tables = parse_tables(input)?;
+        let mut migrations = None;
+        if input.parse::<Option<Token![;]>>()?.is_some() {
+            let key: Ident = input.parse()?;
+            if key != "migrations" {
+                return Err(syn::Error::new_spanned(key, "expected `migrations`"));
+            }
+            input.parse::<Token![=]>()?;
+            migrations = Some(input.parse()?);
+        }
+        Ok(DatabaseInput {
+            db_name,
+            error_type,
+            metadata_type,
+            tables,
+            migrations,
+        })
+    }
+}
+
+// Comma separated tables up to a `;`, each either a type path or `field = path`
+#[cfg(feature = "mdbx")]
+fn parse_tables(input: ParseStream) -> syn::Result<Vec<DbiTable>> {
+    let mut tables = Vec::new();
+    while !input.is_empty() && !input.peek(Token![;]) {
+        if input.peek(Ident) && input.peek2(Token![=]) {
+            let field: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let ty: Type = input.parse()?;
+            tables.push(DbiTable::new(field, ty));
+        } else {
+            let ty: Type = input.parse()?;
+            tables.push(DbiTable::from_type(&ty)?);
+        }
+        if input.parse::<Option<Token![,]>>()?.is_none() {
+            break;
+        }
+    }
+    Ok(tables)
+}
+
+// A table of a generated DBI struct
+#[cfg(feature = "mdbx")]
+struct DbiTable {
+    field: Ident,
+    ty: Type,
+    // Opened but never created, and without write helpers
+    read_only: bool,
+    // May not exist, the handle is an `Option` and the table is never created
+    optional: bool,
+    // `DatabaseFlags` the table was created with besides its own
+    flags: Vec<Ident>,
+}
+
+#[cfg(feature = "mdbx")]
+impl DbiTable {
+    // The field is named after the last path segment, e.g. `TrivialTable` is `trivial_table`
+    fn from_type(ty: &Type) -> syn::Result<Self> {
+        let Type::Path(type_path) = ty else {
+            return Err(syn::Error::new_spanned(ty, "expected a table type path"));
+        };
+        let Some(segment) = type_path.path.segments.last() else {
+            return Err(syn::Error::new_spanned(ty, "expected a table type path"));
+        };
+        let field = Ident::new(
+            &segment.ident.to_string().to_snake_case(),
+            segment.ident.span(),
+        );
+        Ok(Self::new(field, ty.clone()))
+    }
+
+    fn new(field: Ident, ty: Type) -> Self {
+        Self {
+            field,
+            ty,
+            read_only: false,
+            optional: false,
+            flags: Vec::new(),
+        }
+    }
+
+    // Tables owned by the database are created and written by it
+    fn owned(&self) -> bool {
+        !self.read_only && !self.optional
+    }
+}
+
 #[cfg(feature = "mdbx")]
 #[proc_macro]
 pub fn generate_dbi_struct(input: TokenStream) -> TokenStream {
@@ -406,126 +536,384 @@ pub fn generate_dbi_struct(input: TokenStream) -> TokenStream {
         tables,
     } = syn::parse_macro_input!(input as MacroInput);
 
-    let field_names: Vec<_> = tables
+    dbi_struct(&syn::parse_quote!(pub), &struct_name, &error_type, &tables, false)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+// The expansion of `mdbx_database!`
+#[cfg(feature = "mdbx")]
+#[proc_macro]
+pub fn generate_database(input: TokenStream) -> TokenStream {
+    let DatabaseInput {
+        db_name,
+        error_type,
+        metadata_type,
+        tables,
+        migrations,
+    } = syn::parse_macro_input!(input as DatabaseInput);
+
+    let dbi_name = format_ident!("{}Dbi", db_name);
+    let dbi_struct = match dbi_struct(&syn::parse_quote!(pub), &dbi_name, &error_type, &tables, false) {
+        Ok(dbi_struct) => dbi_struct,
+        Err(e) => return e.into_compile_error().into(),
+    };
+    let migrations = migrations.map(|m| quote! { ; migrations = #m });
+    let txn_ext = txn_ext(&syn::parse_quote!(pub), &db_name, &tables);
+
+    let output = quote! {
+        #dbi_struct
+
+        #txn_ext
+
+        mdbx_derive::__mdbx_database_struct!(
+            pub #db_name,
+            #dbi_name,
+            #error_type,
+            #metadata_type
+            #migrations
+        );
+    };
+    output.into()
+}
+
+// With `declared`, the struct is the one `#[derive(MDBXDatabase)]` annotates, whose fields are
+// the `TableHandle`s of the tables, otherwise it is generated with a DBI per table.
+#[cfg(feature = "mdbx")]
+fn dbi_struct(
+    vis: &syn::Visibility,
+    struct_name: &Ident,
+    error_type: &Type,
+    tables: &[DbiTable],
+    declared: bool,
+) -> syn::Result<proc_macro2::TokenStream> {
+    for (i, table) in tables.iter().enumerate() {
+        if tables[..i].iter().any(|t| t.field == table.field) {
+            return Err(syn::Error::new_spanned(
+                &table.field,
+                format!(
+                    "duplicate table field `{}`, name it with `field = path`",
+                    table.field
+                ),
+            ));
+        }
+    }
+
+    let field_names: Vec<_> = tables.iter().map(|t| &t.field).collect();
+    let table_types: Vec<_> = tables.iter().map(|t| &t.ty).collect();
+    let check_names = quote! {
+        mdbx_derive::schema::check_unique_names(&[
+            #( <#table_types as mdbx_derive::MDBXTable>::NAME ),*
+        ])?;
+    };
+
+    let open_statements: Vec<_> = tables
         .iter()
-        .map(|table_type| {
-            let type_path = if let Type::Path(tp) = table_type {
-                tp
+        .map(|table| {
+            let ty = &table.ty;
+            let flags = &table.flags;
+            if flags.is_empty() {
+                quote! {
+                    <#ty as mdbx_derive::MDBXTable>::open_table_checked_tx(&tx).await?
+                }
             } else {
-                panic!("Expected a type path")
-            };
-            let type_ident_str = type_path.path.segments.last().unwrap().ident.to_string();
-            let field_name_str = type_ident_str.to_snake_case();
-            Ident::new(&field_name_str, proc_macro2::Span::call_site())
+                quote! {
+                    {
+                        let dbi = <#ty as mdbx_derive::MDBXTable>::open_table_tx(&tx).await?;
+                        mdbx_derive::schema::check_flags_tx(
+                            &tx,
+                            <#ty as mdbx_derive::MDBXTable>::NAME,
+                            dbi,
+                            <#ty as mdbx_derive::MDBXTable>::FLAGS #( .union(mdbx_derive::mdbx::DatabaseFlags::#flags) )*
+                        )?;
+                        dbi
+                    }
+                }
+            }
         })
         .collect();
 
     let field_statemens: Vec<_> = tables
         .iter()
-        .map(|table_type| {
-            let type_path = if let Type::Path(tp) = table_type {
-                tp
-            } else {
-                panic!("Expected a type path")
-            };
-            let ty = type_path.path.segments.last().unwrap().ident.clone();
-            let field_name_str = ty.to_string().to_snake_case();
-            let ident = Ident::new(&field_name_str, proc_macro2::Span::call_site());
+        .zip(open_statements.iter())
+        .map(|(table, open)| {
+            let ty = &table.ty;
+            let ident = &table.field;
+            let flags = &table.flags;
 
-            quote! {
-                let flags = if <#ty as mdbx_derive::MDBXTable>::DUPSORT {
-                    mdbx_derive::mdbx::DatabaseFlags::DUP_SORT
-                } else {
-                    mdbx_derive::mdbx::DatabaseFlags::default()
-                };
-                let #ident = <#ty as mdbx_derive::MDBXTable>::create_table_tx(&tx, flags).await?;
+            if table.optional {
+                quote! {
+                    let #ident = if mdbx_derive::schema::table_exists_tx(&tx, <#ty as mdbx_derive::MDBXTable>::NAME).await? {
+                        Some(#open)
+                    } else {
+                        None
+                    };
+                }
+            } else if table.read_only {
+                // Checked first, aborting a write transaction after a failed open trips an
+                // assertion of libmdbx
+                quote! {
+                    if !mdbx_derive::schema::table_exists_tx(&tx, <#ty as mdbx_derive::MDBXTable>::NAME).await? {
+                        return Err(mdbx_derive::Error::MissingTable(
+                            <#ty as mdbx_derive::MDBXTable>::NAME.unwrap_or_default()
+                        ).into());
+                    }
+                    let #ident = #open;
+                }
+            } else {
+                // An existing table is opened and checked, creating it with other flags than it
+                // has would fail in the write transaction
+                quote! {
+                    let #ident = if mdbx_derive::schema::table_exists_tx(&tx, <#ty as mdbx_derive::MDBXTable>::NAME).await? {
+                        #open
+                    } else {
+                        <#ty as mdbx_derive::MDBXTable>::create_table_tx(
+                            &tx,
+                            <#ty as mdbx_derive::MDBXTable>::FLAGS #( .union(mdbx_derive::mdbx::DatabaseFlags::#flags) )*
+                        ).await?
+                    };
 
+                }
             }
         })
         .collect();
 
     let ro_field_statemens: Vec<_> = tables
         .iter()
-        .map(|table_type| {
-            let type_path = if let Type::Path(tp) = table_type {
-                tp
-            } else {
-                panic!("Expected a type path")
-            };
-            let ty = type_path.path.segments.last().unwrap().ident.clone();
-            let field_name_str = ty.to_string().to_snake_case();
-            let ident = Ident::new(&field_name_str, proc_macro2::Span::call_site());
+        .zip(open_statements.iter())
+        .map(|(table, open)| {
+            let ty = &table.ty;
+            let ident = &table.field;
 
-            quote! {
-                let #ident = <#ty as mdbx_derive::MDBXTable>::open_table_tx(&tx).await?;
+            if table.optional {
+                quote! {
+                    let #ident = if mdbx_derive::schema::table_exists_tx(&tx, <#ty as mdbx_derive::MDBXTable>::NAME).await? {
+                        Some(#open)
+                    } else {
+                        None
+                    };
+                }
+            } else {
+                quote! {
+                    let #ident = #open;
 
+                }
             }
         })
         .collect();
 
-    let fields = tables
-        .iter()
-        .zip(field_names.iter())
-        .map(|(table_type, field_name)| {
-            let type_path = if let Type::Path(tp) = table_type {
-                tp
-            } else {
-                panic!()
-            };
-            let type_ident_str = type_path.path.segments.last().unwrap().ident.to_string();
-            let doc_string = format!("DBI handle for the `{}` table.", type_ident_str);
+    // The DBI of a table in `self`, an `Option<u32>` for optional tables
+    let field_dbi = |table: &DbiTable| {
+        let field_name = &table.field;
+        match (declared, table.optional) {
+            (false, _) => quote! { self.#field_name },
+            (true, false) => quote! { self.#field_name.dbi() },
+            (true, true) => quote! { self.#field_name.map(|t| t.dbi()) },
+        }
+    };
+
+    let fields = tables.iter().map(|table| {
+        let field_name = &table.field;
+        let ty = &table.ty;
+        let doc_string = format!(
+            "DBI handle for the `{}` table.",
+            quote!(#ty).to_string().replace(' ', "")
+        );
 
+        if table.optional {
+            quote! {
+                #[doc = #doc_string]
+                pub #field_name: Option<u32>,
+            }
+        } else {
             quote! {
                 #[doc = #doc_string]
                 pub #field_name: u32,
             }
-        });
+        }
+    });
+
+    let cache_statements = tables.iter().map(|table| {
+        let dbi = field_dbi(table);
+        let ty = &table.ty;
+
+        // The cache is keyed by the table's own flags, which don't describe this handle
+        if !table.flags.is_empty() {
+            quote! {}
+        } else if table.optional {
+            quote! {
+                if let Some(dbi) = #dbi {
+                    cache.insert::<#ty>(dbi);
+                }
+            }
+        } else {
+            quote! {
+                cache.insert::<#ty>(#dbi);
+            }
+        }
+    });
 
-    let original_type_names: Vec<_> = tables
+    // Owned tables with `flags` are created, fingerprinted and checked with them
+    let owned_types: Vec<_> = tables
         .iter()
-        .map(|table_type| {
-            let type_path = if let Type::Path(tp) = table_type {
-                tp
+        .filter(|t| t.owned())
+        .map(|t| {
+            let ty = &t.ty;
+            let flags = &t.flags;
+            if flags.is_empty() {
+                quote! { #ty }
             } else {
-                panic!("Expected a type path")
-            };
-            let type_ident_str = type_path.path.segments.last().unwrap().ident.to_string();
-            Ident::new(&type_ident_str, proc_macro2::Span::call_site())
+                quote! {
+                    mdbx_derive::WithFlags<#ty, {
+                        mdbx_derive::mdbx::DatabaseFlags::empty() #( .union(mdbx_derive::mdbx::DatabaseFlags::#flags) )* .bits()
+                    }>
+                }
+            }
+        })
+        .collect();
+    let dbi_tables: Vec<_> = tables.iter().filter(|t| !t.optional).collect();
+    let dbi_types: Vec<_> = dbi_tables
+        .iter()
+        .map(|t| {
+            let ty = &t.ty;
+            if t.read_only {
+                quote! { mdbx_derive::DbiTable<#ty, mdbx_derive::Shared> }
+            } else {
+                quote! { mdbx_derive::DbiTable<#ty, mdbx_derive::Owned> }
+            }
+        })
+        .collect();
+    let dbi_fields: Vec<_> = dbi_tables.iter().map(|t| field_dbi(t)).collect();
+    let optional_tables: Vec<_> = tables.iter().filter(|t| t.optional).collect();
+    let optional_dbi_types: Vec<_> = optional_tables
+        .iter()
+        .map(|t| {
+            let ty = &t.ty;
+            if t.read_only {
+                quote! { mdbx_derive::DbiTable<#ty, mdbx_derive::Shared> }
+            } else {
+                quote! { mdbx_derive::DbiTable<#ty, mdbx_derive::Owned> }
+            }
         })
-        .collect(); // The crucial change is here!
+        .collect();
+    let optional_dbi_fields: Vec<_> = optional_tables.iter().map(|t| field_dbi(t)).collect();
+    let optional_count = optional_tables.len();
 
-    let rw_tables: Vec<_> = tables
+    let (read_helpers, write_helpers): (Vec<_>, Vec<_>) = tables
         .iter()
-        .map(|table_type| {
-            let type_path = if let Type::Path(tp) = table_type {
-                tp
+        .map(|table| {
+            let ty = &table.ty;
+            let ident = &table.field;
+            let field_name_str = ident.to_string();
+            let dbi = field_dbi(table);
+            let dbi = if table.optional {
+                quote! {
+                    #dbi.ok_or(mdbx_derive::Error::MissingTable(
+                        <#ty as mdbx_derive::MDBXTable>::NAME.unwrap_or_default()
+                    ))?
+                }
             } else {
-                panic!("Expected a type path")
+                dbi
             };
-            let ty = type_path.path.segments.last().unwrap().ident.clone();
-            let field_name_str = ty.to_string().to_snake_case();
-            let ident = Ident::new(&field_name_str, proc_macro2::Span::call_site());
             let wfname_tx = Ident::new(format!("write_{}_tx", &field_name_str).as_str(), proc_macro2::Span::call_site());
             let rfname_tx = Ident::new(format!("read_{}_tx", &field_name_str).as_str(), proc_macro2::Span::call_site());
             let dfname_tx = Ident::new(format!("del_{}_tx", &field_name_str).as_str(), proc_macro2::Span::call_site());
+            let wmfname_tx = Ident::new(format!("write_many_{}_tx", &field_name_str).as_str(), proc_macro2::Span::call_site());
+            let rmfname_tx = Ident::new(format!("read_many_{}_tx", &field_name_str).as_str(), proc_macro2::Span::call_site());
+            let dmfname_tx = Ident::new(format!("del_many_{}_tx", &field_name_str).as_str(), proc_macro2::Span::call_site());
             let cursor_fname = Ident::new(format!("{}_cursor", &field_name_str).as_str(), proc_macro2::Span::call_site());
-            quote! {
-                pub async fn #wfname_tx
-                (
-                    &self,
-                    tx: &mdbx_derive::mdbx::TransactionAny<mdbx_derive::mdbx::RW>,
-                    key: &<#ty as mdbx_derive::MDBXTable>::Key,
-                    value: &<#ty as mdbx_derive::MDBXTable>::Value,
-                    flags: mdbx_derive::mdbx::WriteFlags
-                ) -> Result<(), mdbx_derive::Error> {
-                    tx.put(
-                        self.#ident,
-                        &<<#ty as mdbx_derive::MDBXTable>::Key as mdbx_derive::KeyObjectEncode>::key_encode(key)?,
-                        &<<#ty as mdbx_derive::MDBXTable>::Value as mdbx_derive::TableObjectEncode>::table_encode(value)?,
-                        flags
-                    ).await?;
-                    Ok(())
+            let table_cursor_fname = Ident::new(format!("{}_table_cursor", &field_name_str).as_str(), proc_macro2::Span::call_site());
+
+            // Read-only tables are never written, whatever the mode of the database
+            let mode = if table.read_only {
+                quote! { mdbx_derive::mdbx::RO }
+            } else {
+                quote! { M }
+            };
+            let handle = match (declared, table.optional) {
+                (false, false) => quote! { mdbx_derive::TableHandle::new(self.#ident) },
+                (false, true) => quote! { self.#ident.map(mdbx_derive::TableHandle::new) },
+                (true, _) => quote! { self.#ident },
+            };
+            let handle = if table.optional {
+                quote! {
+                    pub fn #ident(&self) -> Option<mdbx_derive::TableHandle<#ty, #mode>> {
+                        #handle
+                    }
+                }
+            } else {
+                quote! {
+                    pub fn #ident(&self) -> mdbx_derive::TableHandle<#ty, #mode> {
+                        #handle
+                    }
+                }
+            };
+
+            let write_helpers = if table.read_only {
+                quote! {}
+            } else {
+                quote! {
+                    pub async fn #wfname_tx
+                    (
+                        &self,
+                        tx: &mdbx_derive::mdbx::TransactionAny<mdbx_derive::mdbx::RW>,
+                        key: &<#ty as mdbx_derive::MDBXTable>::Key,
+                        value: &<#ty as mdbx_derive::MDBXTable>::Value,
+                        flags: mdbx_derive::mdbx::WriteFlags
+                    ) -> Result<(), mdbx_derive::Error> {
+                        tx.put(
+                            #dbi,
+                            &<<#ty as mdbx_derive::MDBXTable>::Key as mdbx_derive::KeyObjectEncode>::key_encode(key)?,
+                            &<<#ty as mdbx_derive::MDBXTable>::Value as mdbx_derive::TableObjectEncode>::table_encode(value)?,
+                            flags
+                        ).await?;
+                        Ok(())
+                    }
+
+                    pub async fn #dfname_tx
+                    (
+                        &self,
+                        tx: &mdbx_derive::mdbx::TransactionAny<mdbx_derive::mdbx::RW>,
+                        key: &<#ty as mdbx_derive::MDBXTable>::Key,
+                        value: Option<&<#ty as mdbx_derive::MDBXTable>::Value>
+                    ) -> Result<bool, mdbx_derive::Error> {
+                        let v = value.map(|v| <<#ty as mdbx_derive::MDBXTable>::Value as mdbx_derive::TableObjectEncode>::table_encode(v))
+                                .transpose()?;
+                        Ok(tx.del(
+                            #dbi,
+                            &<<#ty as mdbx_derive::MDBXTable>::Key as mdbx_derive::KeyObjectEncode>::key_encode(key)?,
+                            v.as_ref().map(|t| t.as_slice())
+                        ).await?)
+                    }
+
+                    pub async fn #wmfname_tx
+                    (
+                        &self,
+                        tx: &mdbx_derive::mdbx::TransactionAny<mdbx_derive::mdbx::RW>,
+                        items: impl IntoIterator<Item = (
+                            impl std::borrow::Borrow<<#ty as mdbx_derive::MDBXTable>::Key>,
+                            impl std::borrow::Borrow<<#ty as mdbx_derive::MDBXTable>::Value>,
+                        )>,
+                        flags: mdbx_derive::mdbx::WriteFlags
+                    ) -> Result<usize, mdbx_derive::Error> {
+                        let items = mdbx_derive::batch::encode_items::<#ty>(items)?;
+                        mdbx_derive::batch::put_many_raw::<#ty>(tx, #dbi, items, flags).await
+                    }
+
+                    pub async fn #dmfname_tx
+                    (
+                        &self,
+                        tx: &mdbx_derive::mdbx::TransactionAny<mdbx_derive::mdbx::RW>,
+                        keys: impl IntoIterator<Item = impl std::borrow::Borrow<<#ty as mdbx_derive::MDBXTable>::Key>>
+                    ) -> Result<usize, mdbx_derive::Error> {
+                        let keys = mdbx_derive::batch::encode_keys::<#ty>(keys)?;
+                        mdbx_derive::batch::delete_many_raw(tx, #dbi, keys).await
+                    }
                 }
+            };
+
+            let read_helpers = quote! {
+                #handle
 
                 pub async fn #rfname_tx <K: mdbx_derive::mdbx::TransactionKind>
                 (
@@ -534,7 +922,7 @@ pub fn generate_dbi_struct(input: TokenStream) -> TokenStream {
                     key: &<#ty as mdbx_derive::MDBXTable>::Key
                 ) -> Result<Option< <#ty as mdbx_derive::MDBXTable>::Value >, mdbx_derive::Error> {
                     let v = tx.get::<Vec<u8>>(
-                        self.#ident,
+                        #dbi,
                         &<<#ty as mdbx_derive::MDBXTable>::Key as mdbx_derive::KeyObjectEncode>::key_encode(key)?,
                     ).await?;
                     if let Some(v) = v {
@@ -544,20 +932,14 @@ pub fn generate_dbi_struct(input: TokenStream) -> TokenStream {
                     }
                 }
 
-                pub async fn #dfname_tx
+                pub async fn #rmfname_tx <K: mdbx_derive::mdbx::TransactionKind>
                 (
                     &self,
-                    tx: &mdbx_derive::mdbx::TransactionAny<mdbx_derive::mdbx::RW>,
-                    key: &<#ty as mdbx_derive::MDBXTable>::Key,
-                    value: Option<&<#ty as mdbx_derive::MDBXTable>::Value>
-                ) -> Result<bool, mdbx_derive::Error> {
-                    let v = value.map(|v| <<#ty as mdbx_derive::MDBXTable>::Value as mdbx_derive::TableObjectEncode>::table_encode(v))
-                            .transpose()?;
-                    Ok(tx.del(
-                        self.#ident,
-                        &<<#ty as mdbx_derive::MDBXTable>::Key as mdbx_derive::KeyObjectEncode>::key_encode(key)?,
-                        v.as_ref().map(|t| t.as_slice())
-                    ).await?)
+                    tx: &mdbx_derive::mdbx::TransactionAny<K>,
+                    keys: impl IntoIterator<Item = impl std::borrow::Borrow<<#ty as mdbx_derive::MDBXTable>::Key>>
+                ) -> Result<Vec<Option< <#ty as mdbx_derive::MDBXTable>::Value >>, mdbx_derive::Error> {
+                    let keys = mdbx_derive::batch::encode_keys::<#ty>(keys)?;
+                    mdbx_derive::batch::get_many_raw::<#ty, K>(tx, #dbi, keys).await
                 }
 
                 pub async fn #cursor_fname <K: mdbx_derive::mdbx::TransactionKind>
@@ -565,58 +947,372 @@ pub fn generate_dbi_struct(input: TokenStream) -> TokenStream {
                     &self,
                     tx: &mdbx_derive::mdbx::TransactionAny<K>
                 ) -> Result<mdbx_derive::mdbx::CursorAny<K>, mdbx_derive::Error> {
-                    Ok(tx.cursor_with_dbi(self.#ident).await?)
+                    Ok(tx.cursor_with_dbi(#dbi).await?)
                 }
-            }
+
+                pub async fn #table_cursor_fname <K: mdbx_derive::mdbx::TransactionKind>
+                (
+                    &self,
+                    tx: &mdbx_derive::mdbx::TransactionAny<K>
+                ) -> Result<mdbx_derive::TableCursor<#ty, K>, mdbx_derive::Error> {
+                    Ok(mdbx_derive::TableCursor::new(tx.cursor_with_dbi(#dbi).await?))
+                }
+            };
+
+            (read_helpers, write_helpers)
         })
-        .collect();
+        .unzip();
 
-    let output = quote! {
-        #[derive(Debug, Clone, Copy)]
-        pub struct #struct_name {
-            #( #fields )*
+    let (definition, construct) = if declared {
+        let inits = tables.iter().map(|table| {
+            let field_name = &table.field;
+            if table.optional {
+                quote! { #field_name: #field_name.map(mdbx_derive::TableHandle::new) }
+            } else {
+                quote! { #field_name: mdbx_derive::TableHandle::new(#field_name) }
+            }
+        });
+        (quote! {}, quote! { Self { #( #inits, )* } })
+    } else {
+        (
+            quote! {
+                #vis struct #struct_name<M = mdbx_derive::mdbx::RW> {
+                    #( #fields )*
+                    _mode: std::marker::PhantomData<fn() -> M>,
+                }
+            },
+            quote! {
+                Self {
+                    #( #field_names, )*
+                    _mode: std::marker::PhantomData,
+                }
+            },
+        )
+    };
+
+    Ok(quote! {
+        #definition
+
+        impl<M: mdbx_derive::mdbx::TransactionKind> Clone for #struct_name<M> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl<M: mdbx_derive::mdbx::TransactionKind> Copy for #struct_name<M> {}
+
+        impl<M: mdbx_derive::mdbx::TransactionKind> std::fmt::Debug for #struct_name<M> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(stringify!(#struct_name))
+                    #( .field(stringify!(#field_names), &self.#field_names) )*
+                    .field("mode", &std::any::type_name::<M>())
+                    .finish()
+            }
         }
 
-        impl #struct_name {
+        impl #struct_name<mdbx_derive::mdbx::RW> {
             pub async fn new(
                 env: &mdbx_derive::mdbx::EnvironmentAny,
             ) -> Result<Self, #error_type> {
-                let tx = env.begin_rw_txn().await?;
+                #check_names
+                let tx = mdbx_derive::WriteTxn::begin(env).await?;
+                let result: Result<Self, #error_type> = async {
+                    #(
+                        #field_statemens
+                    )*
 
-                #(
-                    #field_statemens
-                )*
+                    Ok(#construct)
+                }
+                .await;
+                tx.finish(result).await
+            }
+
+            pub async fn new_ro<K: mdbx_derive::mdbx::TransactionKind>(
+                tx: &mdbx_derive::mdbx::TransactionAny<K>
+            ) -> Result<Self, #error_type> {
+                Self::open_existing(tx).await
+            }
 
-                tx.commit().await?;
+            #(
+                #write_helpers
+            )*
+        }
 
-                Ok(Self {
-                    #( #field_names, )*
-                })
+        impl #struct_name<mdbx_derive::mdbx::RO> {
+            // Without the write helpers, for processes that only read.
+            pub async fn new_read_only<K: mdbx_derive::mdbx::TransactionKind>(
+                tx: &mdbx_derive::mdbx::TransactionAny<K>
+            ) -> Result<Self, #error_type> {
+                Self::open_existing(tx).await
             }
+        }
 
-            pub async fn new_ro<K: mdbx_derive::mdbx::TransactionKind>(
+        impl<M: mdbx_derive::mdbx::TransactionKind> #struct_name<M> {
+            async fn open_existing<K: mdbx_derive::mdbx::TransactionKind>(
                 tx: &mdbx_derive::mdbx::TransactionAny<K>
             ) -> Result<Self, #error_type> {
+                #check_names
 
                 #(
                     #ro_field_statemens
                 )*
 
-                Ok(Self {
-                    #( #field_names, )*
-                })
+                Ok(#construct)
+            }
+
+            pub fn fill_dbi_cache(&self, cache: &mdbx_derive::DbiCache) {
+                #(
+                    #cache_statements
+                )*
             }
 
             #(
-                #rw_tables
+                #read_helpers
             )*
         }
 
-        impl mdbx_derive::HasMDBXTables for #struct_name {
+        impl<M: mdbx_derive::mdbx::TransactionKind> mdbx_derive::HasMDBXTables for #struct_name<M> {
             type Error = #error_type;
-            type Tables = mdbx_derive::tuple_list_type!(#( #original_type_names),*);
+            type Tables = mdbx_derive::tuple_list_type!(#( #owned_types ),*);
+        }
+
+        impl<M: mdbx_derive::mdbx::TransactionKind> mdbx_derive::HasMDBXTableDbis for #struct_name<M> {
+            type Mode = M;
+            type DbiTables = mdbx_derive::tuple_list_type!(#( #dbi_types ),*);
+            type OptionalDbiTables = mdbx_derive::tuple_list_type!(#( #optional_dbi_types ),*);
+
+            fn table_dbi(&self, index: usize) -> u32 {
+                [#( #dbi_fields ),*][index]
+            }
+
+            fn optional_table_dbi(&self, index: usize) -> Option<u32> {
+                let dbis: [Option<u32>; #optional_count] = [#( #optional_dbi_fields ),*];
+                dbis[index]
+            }
+        }
+    })
+}
+
+// The table accessors of `DbTxn` for the database `db_name`, e.g. `txn.trivial_table()`. The
+// handles come from the type-indexed tables of the database, so their mode can't be chosen.
+#[cfg(feature = "mdbx")]
+fn txn_ext(vis: &syn::Visibility, db_name: &Ident, tables: &[DbiTable]) -> proc_macro2::TokenStream {
+    let ext_name = format_ident!("{}TxnExt", db_name);
+
+    let (signatures, bodies): (Vec<_>, Vec<_>) = tables
+        .iter()
+        .map(|table| {
+            let ty = &table.ty;
+            let ident = &table.field;
+            // Read-only tables can't be written in a `RW` transaction either
+            let mode = if table.read_only {
+                quote! { mdbx_derive::mdbx::RO }
+            } else {
+                quote! { M }
+            };
+            if table.optional {
+                (
+                    quote! {
+                        fn #ident(&self) -> Option<mdbx_derive::TxnTable<'_, #ty, K, #mode>>
+                    },
+                    quote! {
+                        self.optional_table::<#ty, _>()
+                    },
+                )
+            } else {
+                (
+                    quote! {
+                        fn #ident(&self) -> mdbx_derive::TxnTable<'_, #ty, K, #mode>
+                    },
+                    quote! {
+                        self.table::<#ty, _>()
+                    },
+                )
+            }
+        })
+        .unzip();
+
+    quote! {
+        #vis trait #ext_name<K: mdbx_derive::mdbx::TransactionKind, M: mdbx_derive::mdbx::TransactionKind> {
+            #( #signatures; )*
+        }
+
+        impl<K, M> #ext_name<K, M> for mdbx_derive::DbTxn<'_, K, #db_name<M>>
+        where
+            K: mdbx_derive::mdbx::TransactionKind,
+            M: mdbx_derive::DatabaseEnv,
+        {
+            #(
+                #signatures {
+                    #bodies
+                }
+            )*
+        }
+    }
+}
+
+#[cfg(feature = "mdbx")]
+#[proc_macro_derive(MDBXDatabase, attributes(mdbx, table))]
+pub fn derive_mdbx_database(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    database_impl(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+// The struct is the DBI struct of the database, its fields are the `TableHandle`s of the tables
+// and its one type parameter the access mode. The database is generated next to it as
+// `<Name>Database`, or the name given by `#[mdbx(database = ..)]`. Only the tables that are
+// neither `read_only` nor `optional` are owned by the database, i.e. created, cleared and
+// checked by it.
+#[cfg(feature = "mdbx")]
+fn database_impl(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut error_type: Type = syn::parse_quote!(mdbx_derive::Error);
+    let mut metadata_type: Type = syn::parse_quote!(());
+    let mut db_name = format_ident!("{}Database", input.ident);
+    let mut migrations: Option<syn::Expr> = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("mdbx")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("error") {
+                error_type = meta.value()?.parse()?;
+            } else if meta.path.is_ident("metadata") {
+                metadata_type = meta.value()?.parse()?;
+            } else if meta.path.is_ident("database") {
+                db_name = meta.value()?.parse()?;
+            } else if meta.path.is_ident("migrations") {
+                migrations = Some(meta.value()?.parse()?);
+            } else {
+                return Err(meta.error(
+                    "expected `error`, `metadata`, `database` or `migrations`",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+
+    let Data::Struct(st) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "MDBXDatabase can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &st.fields else {
+        return Err(syn::Error::new_spanned(
+            &st.fields,
+            "expected named fields, one per table",
+        ));
+    };
+
+    if input.generics.params.len() != 1 || input.generics.type_params().count() != 1 {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "expected one type parameter, the access mode, e.g. `struct Tables<M = RW>`",
+        ));
+    }
+
+    let mut tables = Vec::new();
+    for field in &fields.named {
+        let Some(attr) = field.attrs.iter().find(|a| a.path().is_ident("table")) else {
+            return Err(syn::Error::new_spanned(
+                field,
+                "every field of a database must be a #[table]",
+            ));
+        };
+        let mut table = DbiTable {
+            field: field.ident.clone().expect("named field"),
+            ty: field.ty.clone(),
+            read_only: false,
+            optional: false,
+            flags: Vec::new(),
+        };
+        if let Meta::List(_) = &attr.meta {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("read_only") {
+                    table.read_only = true;
+                } else if meta.path.is_ident("optional") {
+                    table.optional = true;
+                } else if meta.path.is_ident("flags") {
+                    let flags =
+                        Punctuated::<Ident, Token![|]>::parse_separated_nonempty(meta.value()?)?;
+                    // Range scans and batch appends compare keys bytewise
+                    if let Some(flag) =
+                        flags.iter().find(|f| *f == "INTEGER_KEY" || *f == "REVERSE_KEY")
+                    {
+                        return Err(syn::Error::new_spanned(
+                            flag,
+                            "INTEGER_KEY and REVERSE_KEY are not supported, keys are compared bytewise",
+                        ));
+                    }
+                    table.flags.extend(flags);
+                } else {
+                    return Err(meta.error("expected `read_only`, `optional` or `flags`"));
+                }
+                Ok(())
+            })?;
         }
+        table.ty = handle_table(&field.ty, table.optional)?;
+        tables.push(table);
+    }
+
+    let vis = &input.vis;
+    let dbi_name = &input.ident;
+    let docs = input.attrs.iter().filter(|a| a.path().is_ident("doc"));
+    let migrations = migrations.map(|m| quote! { ; migrations = #m });
+    let dbi_struct = dbi_struct(vis, dbi_name, &error_type, &tables, true)?;
+    let txn_ext = txn_ext(vis, &db_name, &tables);
+
+    Ok(quote! {
+        #dbi_struct
+
+        #txn_ext
+
+        mdbx_derive::__mdbx_database_struct!(
+            #( #docs )*
+            #vis #db_name,
+            #dbi_name,
+            #error_type,
+            #metadata_type
+            #migrations
+        );
+    })
+}
+
+// The table of a `TableHandle<T, M>` field, or of an `Option<TableHandle<T, M>>` one for
+// optional tables
+#[cfg(feature = "mdbx")]
+fn handle_table(ty: &Type, optional: bool) -> syn::Result<Type> {
+    let handle = if optional {
+        first_type_arg(ty, "Option")
+    } else {
+        Some(ty)
     };
+    handle
+        .and_then(|handle| first_type_arg(handle, "TableHandle"))
+        .cloned()
+        .ok_or_else(|| {
+            let expected = if optional {
+                "expected `Option<TableHandle<Table, M>>` for an optional table"
+            } else {
+                "expected `TableHandle<Table, M>`"
+            };
+            syn::Error::new_spanned(ty, expected)
+        })
+}
 
-    output.into()
+#[cfg(feature = "mdbx")]
+fn first_type_arg<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != name {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    args.args.iter().find_map(|arg| match arg {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
 }
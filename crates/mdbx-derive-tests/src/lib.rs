@@ -3,11 +3,13 @@ mod test {
     use std::io::Cursor;
 
     use mdbx_derive::{
-        KeyAsTableObject, KeyObject, KeyObjectDecode, KeyObjectEncode, TableObjectDecode,
-        TableObjectEncode, ZstdPostcardObject,
+        KeyAsTableObject, KeyObject, KeyObjectDecode, KeyObjectEncode, ObjectCodec, Postcard, Raw,
+        TableObjectDecode, TableObjectEncode, Zstd, ZstdPostcardObject,
     };
     #[cfg(feature = "mdbx")]
-    use mdbx_derive::{mdbx_database, mdbx_table, mdbx_table_def};
+    use mdbx_derive::{
+        mdbx_database, mdbx_dupfixed_table_def, mdbx_dupsort_table_def, mdbx_table, mdbx_table_def,
+    };
     use serde::{Deserialize, Serialize};
 
     #[cfg(any(feature = "simd-json", feature = "serde_json"))]
@@ -36,11 +38,576 @@ mod test {
     mod mdbx_tests {
         use super::*;
 
+        fn temp_path(name: &str) -> std::path::PathBuf {
+            let dir = std::env::temp_dir().join(format!(
+                "mdbx-derive-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            dir
+        }
+
+        fn builder() -> mdbx_derive::mdbx::EnvironmentBuilder {
+            let mut builder = mdbx_derive::mdbx::Environment::builder();
+            builder.set_max_dbs(16);
+            builder
+        }
+
+        fn temp_env(name: &str) -> mdbx_derive::mdbx::EnvironmentAny {
+            mdbx_derive::mdbx::EnvironmentAny::open_local(&temp_path(name), builder()).unwrap()
+        }
+
         #[allow(dead_code)]
         pub struct TrivialTable;
 
         mdbx_table!(TrivialTable, TrivialKey, TrivialObject);
         mdbx_table_def!(TrivialTable2, TrivialKey, TrivialObject);
+        mdbx_table_def!(CodecTable, TrivialKey, Zstd<Postcard<Vec<u64>>>);
+        mdbx_table_def!(
+            SniffedTable,
+            TrivialKey,
+            mdbx_derive::Fallback<
+                Zstd<Postcard<Vec<u64>>>,
+                mdbx_derive::tuple_list_type!(Postcard<Vec<u64>>),
+            >
+        );
+
+        #[tokio::test]
+        async fn sniffed_migration() {
+            use mdbx_derive::{Fallback, MDBXTable, SniffedObject};
+
+            let env = temp_env("sniffed_migration");
+            let tx = env.begin_rw_txn().await.unwrap();
+            let dbi = SniffedTable::create_table_tx(&tx, SniffedTable::FLAGS)
+                .await
+                .unwrap();
+            for b in 0..3u64 {
+                let key = TrivialKey { a: 1, b }.key_encode().unwrap();
+                let legacy = Postcard(vec![b; 4]).table_encode().unwrap();
+                tx.put(dbi, &key, &legacy, Default::default())
+                    .await
+                    .unwrap();
+            }
+            let current = TrivialKey { a: 2, b: 0 };
+            SniffedTable::put_item_tx(
+                &tx,
+                Some(dbi),
+                &current,
+                &Fallback::new(vec![9]),
+                Default::default(),
+            )
+            .await
+            .unwrap();
+            tx.commit().await.unwrap();
+
+            let value = SniffedTable::get_item(&env, &TrivialKey { a: 1, b: 2 })
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(value.is_legacy());
+            // Only the items in the legacy format are rewritten
+            assert_eq!(SniffedTable::migrate_items(&env).await.unwrap(), 3);
+            assert_eq!(SniffedTable::migrate_items(&env).await.unwrap(), 0);
+            let value = SniffedTable::get_item(&env, &TrivialKey { a: 1, b: 2 })
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(!value.is_legacy());
+            assert_eq!(*value, vec![2; 4]);
+            let value = SniffedTable::get_item(&env, &current)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(*value, vec![9]);
+        }
+
+        #[tokio::test]
+        async fn cursor_stream() {
+            use mdbx_derive::MDBXTable;
+            use mdbx_derive::futures::TryStreamExt;
+
+            let env = temp_env("cursor_stream");
+            let tx = env.begin_rw_txn().await.unwrap();
+            let dbi = TrivialTable::create_table_tx(&tx, TrivialTable::FLAGS)
+                .await
+                .unwrap();
+            // Written out of order, streamed in key order
+            for (a, b) in [(2, 0), (1, 1), (1, 0)] {
+                let value = TrivialObject { a: a * 10, b };
+                TrivialTable::put_item_tx(
+                    &tx,
+                    Some(dbi),
+                    &TrivialKey { a, b },
+                    &value,
+                    Default::default(),
+                )
+                .await
+                .unwrap();
+            }
+            tx.commit().await.unwrap();
+
+            let tx = env.begin_ro_txn().await.unwrap();
+            let cursor = TrivialTable::cursor_tx(&tx, None).await.unwrap();
+            let items: Vec<_> = cursor.into_stream().try_collect().await.unwrap();
+            let items: Vec<_> = items.iter().map(|(k, v)| (k.a, k.b, v.a, v.b)).collect();
+            assert_eq!(items, [(1, 0, 10, 0), (1, 1, 10, 1), (2, 0, 20, 0)]);
+        }
+
+        #[tokio::test]
+        async fn range_scans() {
+            use mdbx_derive::MDBXTable;
+            use mdbx_derive::futures::TryStreamExt;
+            use std::ops::Bound;
+
+            fn keys(items: Vec<(TrivialKey, TrivialObject)>) -> Vec<(u64, u64)> {
+                items.iter().map(|(k, _)| (k.a, k.b)).collect()
+            }
+
+            let env = temp_env("range_scans");
+            let tx = env.begin_rw_txn().await.unwrap();
+            let dbi = TrivialTable::create_table_tx(&tx, TrivialTable::FLAGS)
+                .await
+                .unwrap();
+            for a in [1, 2, u64::MAX] {
+                for b in 0..3 {
+                    TrivialTable::put_item_tx(
+                        &tx,
+                        Some(dbi),
+                        &TrivialKey { a, b },
+                        &TrivialObject { a, b },
+                        Default::default(),
+                    )
+                    .await
+                    .unwrap();
+                }
+            }
+            tx.commit().await.unwrap();
+
+            let tx = env.begin_ro_txn().await.unwrap();
+            let key = |a, b| TrivialKey { a, b };
+            let items = TrivialTable::range_tx(&tx, None, key(1, 1)..=key(2, 0))
+                .await
+                .unwrap();
+            assert_eq!(
+                keys(items.try_collect().await.unwrap()),
+                [(1, 1), (1, 2), (2, 0)]
+            );
+            let bounds = (Bound::Excluded(key(1, 1)), Bound::Excluded(key(2, 1)));
+            let items = TrivialTable::range_tx(&tx, None, bounds).await.unwrap();
+            assert_eq!(keys(items.try_collect().await.unwrap()), [(1, 2), (2, 0)]);
+            let items = TrivialTable::range_tx(&tx, None, key(2, 2)..)
+                .await
+                .unwrap();
+            assert_eq!(
+                keys(items.try_collect().await.unwrap()),
+                [(2, 2), (u64::MAX, 0), (u64::MAX, 1), (u64::MAX, 2)]
+            );
+            let items = TrivialTable::range_rev_tx(&tx, None, key(1, 1)..key(2, 1))
+                .await
+                .unwrap();
+            assert_eq!(
+                keys(items.try_collect().await.unwrap()),
+                [(2, 0), (1, 2), (1, 1)]
+            );
+            let bounds = (Bound::Excluded(key(1, 0)), Bound::Excluded(key(1, 2)));
+            let items = TrivialTable::range_rev_tx(&tx, None, bounds).await.unwrap();
+            assert_eq!(keys(items.try_collect().await.unwrap()), [(1, 1)]);
+
+            let (one, three) = (1u64.to_be_bytes(), 3u64.to_be_bytes());
+            let items = TrivialTable::prefix_tx(&tx, None, &one).await.unwrap();
+            assert_eq!(
+                keys(items.try_collect().await.unwrap()),
+                [(1, 0), (1, 1), (1, 2)]
+            );
+            let items = TrivialTable::prefix_rev_tx(&tx, None, &one).await.unwrap();
+            assert_eq!(
+                keys(items.try_collect().await.unwrap()),
+                [(1, 2), (1, 1), (1, 0)]
+            );
+            // A prefix of 0xFF bytes has no upper bound, it runs to the end of the table
+            for prefix in [&[0xFF][..], &u64::MAX.to_be_bytes()] {
+                let items = TrivialTable::prefix_tx(&tx, None, prefix).await.unwrap();
+                assert_eq!(
+                    keys(items.try_collect().await.unwrap()),
+                    [(u64::MAX, 0), (u64::MAX, 1), (u64::MAX, 2)]
+                );
+                let items = TrivialTable::prefix_rev_tx(&tx, None, prefix)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    keys(items.try_collect().await.unwrap()),
+                    [(u64::MAX, 2), (u64::MAX, 1), (u64::MAX, 0)]
+                );
+            }
+            let items = TrivialTable::prefix_tx(&tx, None, &three).await.unwrap();
+            assert!(keys(items.try_collect().await.unwrap()).is_empty());
+        }
+
+        mdbx_dupsort_table_def!(TrivialDupTable, TrivialKey, Raw<u64>);
+
+        #[tokio::test]
+        async fn dup_reads() {
+            use mdbx_derive::{MDBXDupSortTable, MDBXTable};
+
+            let env = temp_env("dup_reads");
+            let key = TrivialKey { a: 1, b: 0 };
+            let other = TrivialKey { a: 2, b: 0 };
+            let tx = env.begin_rw_txn().await.unwrap();
+            let dbi = TrivialDupTable::create_table_tx(&tx, TrivialDupTable::FLAGS)
+                .await
+                .unwrap();
+            let items = [
+                (&key, Raw(5)),
+                (&key, Raw(1)),
+                (&key, Raw(3)),
+                (&key, Raw(2)),
+                (&other, Raw(7)),
+            ];
+            TrivialDupTable::put_many_tx(&tx, Some(dbi), items, Default::default())
+                .await
+                .unwrap();
+
+            let values = |dups: Vec<Raw<u64>>| dups.into_iter().map(|v| v.0).collect::<Vec<_>>();
+            let dups = TrivialDupTable::get_dups_tx(&tx, None, &key).await.unwrap();
+            assert_eq!(values(dups), [1, 2, 3, 5]);
+            assert_eq!(
+                TrivialDupTable::count_dups_tx(&tx, None, &key)
+                    .await
+                    .unwrap(),
+                4
+            );
+            let first = TrivialDupTable::first_dup_tx(&tx, None, &key)
+                .await
+                .unwrap();
+            assert_eq!(first.map(|v| v.0), Some(1));
+            let last = TrivialDupTable::last_dup_tx(&tx, None, &key).await.unwrap();
+            assert_eq!(last.map(|v| v.0), Some(5));
+            // The range search stays within the duplicates of the key
+            let next = TrivialDupTable::get_both_range_tx(&tx, None, &key, &Raw(4))
+                .await
+                .unwrap();
+            assert_eq!(next.map(|v| v.0), Some(5));
+            let next = TrivialDupTable::get_both_range_tx(&tx, None, &key, &Raw(6))
+                .await
+                .unwrap();
+            assert!(next.is_none());
+            let found = TrivialDupTable::get_both_tx(&tx, None, &key, &Raw(3))
+                .await
+                .unwrap();
+            assert_eq!(found.map(|v| v.0), Some(3));
+
+            assert!(
+                TrivialDupTable::del_dup_tx(&tx, None, &key, &Raw(1))
+                    .await
+                    .unwrap()
+            );
+            assert!(
+                !TrivialDupTable::del_dup_tx(&tx, None, &key, &Raw(1))
+                    .await
+                    .unwrap()
+            );
+            let dups = TrivialDupTable::get_dups_tx(&tx, None, &key).await.unwrap();
+            assert_eq!(values(dups), [2, 3, 5]);
+            assert_eq!(
+                TrivialDupTable::count_dups_tx(&tx, None, &key)
+                    .await
+                    .unwrap(),
+                3
+            );
+            let dups = TrivialDupTable::get_dups_tx(&tx, None, &other)
+                .await
+                .unwrap();
+            assert_eq!(values(dups), [7]);
+        }
+
+        mdbx_dupfixed_table_def!(TrivialIndexTable, TrivialKey, Raw<u64>);
+
+        #[tokio::test]
+        async fn dup_multiple_reads() {
+            use mdbx_derive::{MDBXDupFixedTable, MDBXTable};
+
+            let env = temp_env("dup_multiple_reads");
+            let key = TrivialKey { a: 1, b: 0 };
+            let other = TrivialKey { a: 2, b: 0 };
+            let tx = env.begin_rw_txn().await.unwrap();
+            let dbi = TrivialIndexTable::create_table_tx(&tx, TrivialIndexTable::FLAGS)
+                .await
+                .unwrap();
+            let items = (0..2000u64)
+                .map(|v| (&key, Raw(v)))
+                .chain((0..3u64).map(|v| (&other, Raw(v))));
+            TrivialIndexTable::put_many_tx(&tx, Some(dbi), items, Default::default())
+                .await
+                .unwrap();
+            tx.commit().await.unwrap();
+
+            let tx = env.begin_ro_txn().await.unwrap();
+            // The duplicates of the key span several pages
+            let dups = TrivialIndexTable::get_dups_multiple_tx(&tx, None, &key)
+                .await
+                .unwrap();
+            assert_eq!(
+                dups.into_iter().map(|v| v.0).collect::<Vec<_>>(),
+                (0..2000).collect::<Vec<_>>()
+            );
+            let dups = TrivialIndexTable::get_dups_multiple_tx(&tx, None, &other)
+                .await
+                .unwrap();
+            assert_eq!(dups.into_iter().map(|v| v.0).collect::<Vec<_>>(), [0, 1, 2]);
+            let dups =
+                TrivialIndexTable::get_dups_multiple_tx(&tx, None, &TrivialKey { a: 3, b: 0 })
+                    .await
+                    .unwrap();
+            assert!(dups.is_empty());
+
+            let mut cursor = TrivialIndexTable::cursor_tx(&tx, None).await.unwrap();
+            cursor.seek_exact(&key).await.unwrap().unwrap();
+            let first = cursor.get_multiple().await.unwrap().unwrap();
+            assert!(!first.is_empty() && first.len() < 2000);
+            let mut values: Vec<_> = first.into_iter().map(|v| v.0).collect();
+            // The pages stop at the last duplicate of the key
+            while let Some((k, page)) = cursor.next_multiple().await.unwrap() {
+                assert_eq!((k.a, k.b), (key.a, key.b));
+                values.extend(page.into_iter().map(|v| v.0));
+            }
+            assert_eq!(values, (0..2000).collect::<Vec<_>>());
+        }
+
+        #[tokio::test]
+        async fn single_shot_helpers_commit() {
+            use mdbx_derive::MDBXTable;
+
+            let path = temp_path("single_shot_helpers_commit");
+            let key = TrivialKey { a: 1, b: 2 };
+            {
+                let env = mdbx_derive::mdbx::EnvironmentAny::open_local(&path, builder()).unwrap();
+                TrivialTable2::create_table(&env, TrivialTable2::FLAGS)
+                    .await
+                    .unwrap();
+                let value = TrivialObject { a: 3, b: 4 };
+                TrivialTable::put_item(&env, &key, &value, Default::default())
+                    .await
+                    .unwrap();
+            }
+
+            let env = mdbx_derive::mdbx::EnvironmentAny::open_local(&path, builder()).unwrap();
+            let tx = env.begin_ro_txn().await.unwrap();
+            assert!(
+                mdbx_derive::schema::table_exists_tx(&tx, TrivialTable2::NAME)
+                    .await
+                    .unwrap()
+            );
+            let value = TrivialTable::get_item(&env, &key).await.unwrap().unwrap();
+            assert_eq!((value.a, value.b), (3, 4));
+        }
+
+        #[tokio::test]
+        async fn dup_counts() {
+            use mdbx_derive::{MDBXDupFixedTable, MDBXDupSortTable, MDBXTable};
+
+            let env = temp_env("dup_counts");
+            let key = TrivialKey { a: 1, b: 0 };
+            let other = TrivialKey { a: 2, b: 0 };
+            let tx = env.begin_rw_txn().await.unwrap();
+            let dbi = TrivialIndexTable::create_table_tx(&tx, TrivialIndexTable::FLAGS)
+                .await
+                .unwrap();
+            TrivialIndexTable::put_many_tx(
+                &tx,
+                Some(dbi),
+                (0..2000u64).map(|v| (TrivialKey { a: 1, b: 0 }, Raw(v))),
+                Default::default(),
+            )
+            .await
+            .unwrap();
+            TrivialIndexTable::put_many_tx(
+                &tx,
+                Some(dbi),
+                (0..3u64).map(|v| (TrivialKey { a: 2, b: 0 }, Raw(v))),
+                Default::default(),
+            )
+            .await
+            .unwrap();
+
+            for (key, count) in [(&key, 2000), (&other, 3), (&TrivialKey { a: 3, b: 0 }, 0)] {
+                assert_eq!(
+                    TrivialIndexTable::count_dups_tx(&tx, Some(dbi), key).await.unwrap(),
+                    count
+                );
+                assert_eq!(
+                    TrivialIndexTable::count_dups_multiple_tx(&tx, Some(dbi), key)
+                        .await
+                        .unwrap(),
+                    count
+                );
+            }
+        }
+
+        #[tokio::test]
+        async fn flags_mismatch() {
+            use mdbx_derive::{MDBXTable, mdbx::DatabaseFlags, schema::dbi_flags_tx};
+
+            let env = temp_env("flags_mismatch");
+            let tx = env.begin_rw_txn().await.unwrap();
+            TrivialDupTable::create_table_tx(&tx, TrivialDupTable::FLAGS)
+                .await
+                .unwrap();
+            // The DUP_FIXED table under the name of a DUP_SORT one
+            tx.create_db(TrivialIndexTable::NAME, DatabaseFlags::DUP_SORT)
+                .await
+                .unwrap();
+            tx.commit().await.unwrap();
+
+            let tx = env.begin_ro_txn().await.unwrap();
+            let dbi = TrivialIndexTable::open_table_tx(&tx).await.unwrap();
+            let flags = dbi_flags_tx(&tx, dbi).unwrap();
+            assert_eq!(flags.map(|f| f.bits()), Some(DatabaseFlags::DUP_SORT.bits()));
+            TrivialDupTable::open_table_checked_tx(&tx).await.unwrap();
+            let err = TrivialIndexTable::open_table_checked_tx(&tx).await.unwrap_err();
+            assert!(
+                matches!(
+                    &err,
+                    mdbx_derive::Error::FlagsMismatch { table, expected, actual }
+                        if table == "TrivialIndexTable"
+                            && *expected == TrivialIndexTable::FLAGS.bits()
+                            && *actual == DatabaseFlags::DUP_SORT.bits()
+                ),
+                "{err:?}"
+            );
+        }
+
+        #[tokio::test]
+        async fn owned_flags_mismatch() {
+            use mdbx_derive::{MDBXTable, mdbx::DatabaseFlags};
+
+            let env = temp_env("owned_flags_mismatch");
+            let tx = env.begin_rw_txn().await.unwrap();
+            tx.create_db(TrivialTable::NAME, DatabaseFlags::DUP_SORT)
+                .await
+                .unwrap();
+            tx.commit().await.unwrap();
+
+            let err = TrivialDatabaseDbi::new(&env).await.unwrap_err();
+            assert!(
+                matches!(
+                    &err,
+                    mdbx_derive::Error::FlagsMismatch { table, expected, actual }
+                        if table == "TrivialTable"
+                            && *expected == 0
+                            && *actual == DatabaseFlags::DUP_SORT.bits()
+                ),
+                "{err:?}"
+            );
+        }
+
+        #[tokio::test]
+        async fn batch_writes() {
+            use mdbx_derive::{MDBXDupSortTable, MDBXTable};
+
+            let env = temp_env("batch_writes");
+            let tx = env.begin_rw_txn().await.unwrap();
+            let dbi = TrivialTable2::create_table_tx(&tx, TrivialTable2::FLAGS)
+                .await
+                .unwrap();
+            let key = |b| TrivialKey { a: 1, b };
+            let obj = |a| TrivialObject { a, b: 0 };
+
+            // Sorted into an empty table, then unsorted around and past the existing keys,
+            // where the last value of a repeated key wins
+            let batches: [(&[(u64, u64)], usize); 3] = [
+                (&[(10, 1), (11, 1), (12, 1)], 3),
+                (&[(20, 2), (5, 2), (11, 2), (15, 2), (11, 3)], 4),
+                (&[(30, 4), (31, 4)], 2),
+            ];
+            for (batch, count) in batches {
+                let written = TrivialTable2::put_many_tx(
+                    &tx,
+                    Some(dbi),
+                    batch.iter().map(|(b, a)| (key(*b), obj(*a))),
+                    Default::default(),
+                )
+                .await
+                .unwrap();
+                assert_eq!(written, count);
+            }
+            let keys = [5, 10, 11, 12, 15, 20, 30, 31, 40];
+            let values = TrivialTable2::get_many_tx(&tx, Some(dbi), keys.map(key))
+                .await
+                .unwrap();
+            let values: Vec<_> = values.into_iter().map(|v| v.map(|v| v.a)).collect();
+            assert_eq!(
+                values,
+                [2, 1, 3, 1, 2, 2, 4, 4]
+                    .map(Some)
+                    .into_iter()
+                    .chain([None])
+                    .collect::<Vec<_>>()
+            );
+
+            // INTEGER_DUP sorts the big-endian values of Raw<u64> differently than bytewise
+            let dbi = RenamedIndexTable::create_table_tx(&tx, RenamedIndexTable::FLAGS)
+                .await
+                .unwrap();
+            for values in [[1, 256, 2], [65536, 3, 4]] {
+                RenamedIndexTable::put_many_tx(
+                    &tx,
+                    Some(dbi),
+                    values.map(|v| (1, Raw(v))),
+                    Default::default(),
+                )
+                .await
+                .unwrap();
+            }
+            let mut dups: Vec<_> = RenamedIndexTable::get_dups_tx(&tx, Some(dbi), &1)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|v| v.0)
+                .collect();
+            dups.sort();
+            assert_eq!(dups, [1, 2, 3, 4, 256, 65536]);
+        }
+
+        mdbx_table_def!(
+            RenamedTable,
+            u64,
+            Raw<u64>;
+            name = "renamed"
+        );
+        mdbx_dupfixed_table_def!(
+            RenamedIndexTable,
+            u64,
+            Raw<u64>,
+            mdbx_derive::Error;
+            flags = INTEGER_DUP
+        );
+
+        #[test]
+        #[allow(deprecated)]
+        fn deprecated_match_name() {
+            use mdbx_derive::MatchName;
+
+            let tables = mdbx_derive::tuple_list!(TrivialTable, TrivialTable2);
+            assert!(tables.match_name::<TrivialTable2>(Some("TrivialTable2")).is_some());
+            assert!(tables.match_name::<TrivialTable2>(Some("TrivialTable")).is_none());
+        }
+
+        #[test]
+        fn table_options() {
+            use mdbx_derive::{MDBXTable, mdbx::DatabaseFlags};
+
+            assert_eq!(RenamedTable::NAME, Some("renamed"));
+            assert!(RenamedTable::FLAGS.is_empty());
+            const { assert!(!RenamedTable::DUPSORT) };
+            assert_eq!(RenamedIndexTable::NAME, Some("RenamedIndexTable"));
+            assert_eq!(
+                RenamedIndexTable::FLAGS.bits(),
+                (DatabaseFlags::DUP_SORT | DatabaseFlags::DUP_FIXED | DatabaseFlags::INTEGER_DUP)
+                    .bits()
+            );
+        }
 
         mdbx_database!(TrivialDatabase, mdbx_derive::Error, (), TrivialTable);
         mdbx_database!(
@@ -50,6 +617,1244 @@ mod test {
             TrivialTable,
             TrivialTable2
         );
+
+        #[tokio::test]
+        async fn table_handles() {
+            let env = temp_env("table_handles");
+            let dbis = TrivialDatabase2Dbi::new(&env).await.unwrap();
+            let db = TrivialDatabase2::new(env, dbis);
+            let table = db.dbis.trivial_table();
+            assert_eq!(table.dbi(), db.dbis.trivial_table);
+            let key = TrivialKey { a: 1, b: 2 };
+            let tx = db.env.begin_rw_txn().await.unwrap();
+            table
+                .put(&tx, &key, &TrivialObject { a: 3, b: 4 }, Default::default())
+                .await
+                .unwrap();
+            table
+                .put(
+                    &tx,
+                    &TrivialKey { a: 0, b: 0 },
+                    &TrivialObject::default(),
+                    Default::default(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(table.stat(&tx).await.unwrap().entries, 2);
+            assert_eq!(db.dbis.trivial_table2().stat(&tx).await.unwrap().entries, 0);
+            let (first, _) = table
+                .cursor(&tx)
+                .await
+                .unwrap()
+                .first()
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!((first.a, first.b), (0, 0));
+            assert_eq!(
+                table.get(&tx, &key).await.unwrap().map(|v| (v.a, v.b)),
+                Some((3, 4))
+            );
+
+            assert!(table.del(&tx, &key, None).await.unwrap());
+            assert!(!table.del(&tx, &key, None).await.unwrap());
+            assert!(table.get(&tx, &key).await.unwrap().is_none());
+            table.clear(&tx).await.unwrap();
+            assert!(
+                table
+                    .cursor(&tx)
+                    .await
+                    .unwrap()
+                    .first()
+                    .await
+                    .unwrap()
+                    .is_none()
+            );
+        }
+
+        #[tokio::test]
+        async fn indexed_tables() {
+            use mdbx_derive::HasMDBXTableDbis;
+
+            let env = temp_env("indexed_tables");
+            let dbis = TrivialDatabase2Dbi::new(&env).await.unwrap();
+            let db = TrivialDatabase2::new(env, dbis);
+            // Tables are found by type, in the order they were declared
+            assert_eq!(db.table::<TrivialTable, _>().dbi(), db.dbis.trivial_table);
+            assert_eq!(db.table::<TrivialTable2, _>().dbi(), db.dbis.trivial_table2);
+            assert_eq!(db.table_dbi(1), db.dbis.trivial_table2);
+
+            let key = TrivialKey { a: 1, b: 2 };
+            let tx = db.env.begin_rw_txn().await.unwrap();
+            db.put::<TrivialTable2, _>(
+                &tx,
+                &key,
+                &TrivialObject { a: 3, b: 4 },
+                Default::default(),
+            )
+            .await
+            .unwrap();
+            assert!(
+                db.get::<TrivialTable, _>(&tx, &key)
+                    .await
+                    .unwrap()
+                    .is_none()
+            );
+            let (first, _) = db
+                .cursor::<TrivialTable2, _, _>(&tx)
+                .await
+                .unwrap()
+                .first()
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!((first.a, first.b), (1, 2));
+            // Deleting from the other table leaves the item alone
+            db.del::<TrivialTable, _>(&tx, &key, None).await.unwrap();
+            let value = db.get::<TrivialTable2, _>(&tx, &key).await.unwrap();
+            assert_eq!(value.map(|v| (v.a, v.b)), Some((3, 4)));
+        }
+
+        #[tokio::test]
+        async fn db_txn_tables() {
+            use mdbx_derive::MDBXDatabase;
+
+            let env = temp_env("db_txn_tables");
+            let dbis = TrivialDatabase2Dbi::new(&env).await.unwrap();
+            let db = TrivialDatabase2::new(env, dbis);
+            let key = TrivialKey { a: 1, b: 2 };
+            let value = TrivialObject { a: 3, b: 4 };
+
+            // Aborting drops the writes
+            let txn = db.begin_rw().await.unwrap();
+            txn.trivial_table()
+                .put(&key, &value, Default::default())
+                .await
+                .unwrap();
+            txn.abort();
+            let txn = db.begin_rw().await.unwrap();
+            assert!(txn.trivial_table().get(&key).await.unwrap().is_none());
+            txn.trivial_table()
+                .put(&key, &value, Default::default())
+                .await
+                .unwrap();
+            assert!(
+                !txn.table::<TrivialTable2, _>()
+                    .del(&key, None)
+                    .await
+                    .unwrap()
+            );
+            txn.commit().await.unwrap();
+
+            let txn = db.begin_ro().await.unwrap();
+            let stored = txn.trivial_table().get(&key).await.unwrap();
+            assert_eq!(stored.map(|v| (v.a, v.b)), Some((3, 4)));
+            // The raw transaction is still at hand
+            let mut cursor = txn
+                .cursor_with_dbi(txn.trivial_table2().dbi())
+                .await
+                .unwrap();
+            assert!(cursor.first::<Vec<u8>, Vec<u8>>().await.unwrap().is_none());
+        }
+
+        #[tokio::test]
+        async fn nested_savepoints() {
+            use mdbx_derive::MDBXDatabase;
+
+            let env = temp_env("nested_savepoints");
+            let dbis = TrivialDatabase2Dbi::new(&env).await.unwrap();
+            let db = TrivialDatabase2::new(env, dbis);
+            let key = TrivialKey { a: 1, b: 2 };
+            let value = TrivialObject { a: 3, b: 4 };
+
+            let mut txn = db.begin_rw().await.unwrap();
+            txn.trivial_table2()
+                .put(&key, &value, Default::default())
+                .await
+                .unwrap();
+            let mut sp = txn.savepoint().await.unwrap();
+            sp.trivial_table()
+                .put(&key, &value, Default::default())
+                .await
+                .unwrap();
+            // Rolling back the inner savepoint keeps the writes of the outer one
+            let inner = sp.savepoint().await.unwrap();
+            assert!(inner.trivial_table2().del(&key, None).await.unwrap());
+            inner.rollback();
+            assert!(sp.trivial_table2().get(&key).await.unwrap().is_some());
+            sp.commit().await.unwrap();
+            txn.commit().await.unwrap();
+
+            let txn = db.begin_ro().await.unwrap();
+            assert!(txn.trivial_table().get(&key).await.unwrap().is_some());
+            assert!(txn.trivial_table2().get(&key).await.unwrap().is_some());
+        }
+
+        #[tokio::test]
+        async fn read_only_helpers() {
+            use mdbx_derive::{HasMDBXTableDbis, MDBXDatabase, MDBXTable};
+
+            let path = temp_path("read_only_helpers");
+            let url = path.to_str().unwrap();
+            // A read-only open never creates the environment
+            let opened =
+                TrivialDatabase2::<mdbx_derive::mdbx::RO>::open_tables_read_only_with_defaults(
+                    url,
+                    builder(),
+                )
+                .await;
+            assert!(opened.is_err());
+
+            let db = <TrivialDatabase2>::open_create_tables_with_defaults(url, builder())
+                .await
+                .unwrap();
+            let key = TrivialKey { a: 1, b: 2 };
+            TrivialTable::put_item(&db, &key, &TrivialObject { a: 3, b: 4 }, Default::default())
+                .await
+                .unwrap();
+            TrivialTable2::put_item(&db, &key, &TrivialObject { a: 5, b: 6 }, Default::default())
+                .await
+                .unwrap();
+            drop(db);
+
+            let db =
+                TrivialDatabase2::<mdbx_derive::mdbx::RO>::open_tables_read_only_with_defaults(
+                    url,
+                    builder(),
+                )
+                .await
+                .unwrap();
+            assert!(db.metadata().await.unwrap().is_none());
+            // The environment of a read-only database only begins read-only transactions
+            let tx = db.env.begin_ro_txn().await.unwrap();
+            assert_eq!(db.dbis.trivial_table().stat(&tx).await.unwrap().entries, 1);
+            let value = db.dbis.read_trivial_table_tx(&tx, &key).await.unwrap();
+            assert_eq!(value.map(|v| (v.a, v.b)), Some((3, 4)));
+            let value = db.get::<TrivialTable2, _>(&tx, &key).await.unwrap();
+            assert_eq!(value.map(|v| (v.a, v.b)), Some((5, 6)));
+        }
+
+        struct EntriesVisitor<'a> {
+            tx: &'a mdbx_derive::mdbx::TransactionAny<mdbx_derive::mdbx::RO>,
+            entries: Vec<(Option<&'static str>, usize)>,
+        }
+
+        impl mdbx_derive::TableVisitor<mdbx_derive::Error> for EntriesVisitor<'_> {
+            async fn visit<T>(&mut self) -> Result<(), mdbx_derive::Error>
+            where
+                T: mdbx_derive::MDBXTable,
+                mdbx_derive::Error: From<T::Error>,
+            {
+                let stat = T::stat_tx(self.tx, None).await?;
+                self.entries.push((T::NAME, stat.entries));
+                Ok(())
+            }
+        }
+
+        #[tokio::test]
+        async fn table_visitor() {
+            use mdbx_derive::{MDBXDatabase, MDBXTable};
+
+            let env = temp_env("table_visitor");
+            let dbis = TrivialDatabase2Dbi::new(&env).await.unwrap();
+            let db = TrivialDatabase2::new(env, dbis);
+            let items = (0..3).map(|a| (TrivialKey { a, b: 0 }, TrivialObject::default()));
+            TrivialTable2::put_many(&db, items, Default::default())
+                .await
+                .unwrap();
+
+            let tx = db.env.begin_ro_txn().await.unwrap();
+            let mut visitor = EntriesVisitor {
+                tx: &tx,
+                entries: Vec::new(),
+            };
+            db.visit_tables(&mut visitor).await.unwrap();
+            // The tables are visited in the order they were declared
+            assert_eq!(
+                visitor.entries,
+                [(Some("TrivialTable"), 0), (Some("TrivialTable2"), 3)]
+            );
+        }
+
+        mod legacy {
+            use super::*;
+
+            mdbx_table_def!(TrivialTable, TrivialKey, TrivialObject; name = "LegacyTrivialTable");
+        }
+
+        // Tables from other modules need not be in scope, and clashing names get an alias
+        mdbx_database!(
+            PathDatabase,
+            mdbx_derive::Error,
+            (),
+            self::TrivialTable,
+            legacy_trivial_table = legacy::TrivialTable
+        );
+
+        mod clash {
+            use super::*;
+
+            mdbx_table_def!(TrivialTable, TrivialKey, TrivialObject);
+        }
+
+        // An alias only names the field, both tables are "TrivialTable" on disk
+        mdbx_database!(
+            ClashDatabase,
+            mdbx_derive::Error,
+            (),
+            TrivialTable,
+            clash_trivial_table = clash::TrivialTable
+        );
+
+        #[tokio::test]
+        async fn duplicate_table_names() {
+            let env = temp_env("duplicate_table_names");
+            let is_duplicate = |err: &mdbx_derive::Error| {
+                matches!(err, mdbx_derive::Error::DuplicateTable("TrivialTable"))
+            };
+            let err = ClashDatabaseDbi::new(&env).await.unwrap_err();
+            assert!(is_duplicate(&err), "{err:?}");
+            let tx = env.begin_ro_txn().await.unwrap();
+            let err = ClashDatabaseDbi::new_ro(&tx).await.unwrap_err();
+            assert!(is_duplicate(&err), "{err:?}");
+            drop(tx);
+
+            // Distinct names pass
+            PathDatabaseDbi::new(&env).await.unwrap();
+        }
+
+        #[tokio::test]
+        async fn path_tables() {
+            use mdbx_derive::{MDBXTable, schema::table_exists_tx};
+
+            let env = temp_env("path_tables");
+            let dbis = PathDatabaseDbi::new(&env).await.unwrap();
+            let db = PathDatabase::new(env, dbis);
+            let key = TrivialKey { a: 1, b: 2 };
+            let tx = db.env.begin_rw_txn().await.unwrap();
+            db.dbis
+                .write_trivial_table_tx(
+                    &tx,
+                    &key,
+                    &TrivialObject { a: 3, b: 4 },
+                    Default::default(),
+                )
+                .await
+                .unwrap();
+            db.dbis
+                .write_legacy_trivial_table_tx(
+                    &tx,
+                    &key,
+                    &TrivialObject { a: 5, b: 6 },
+                    Default::default(),
+                )
+                .await
+                .unwrap();
+            tx.commit().await.unwrap();
+
+            // The aliased table is a table of its own
+            let tx = db.env.begin_ro_txn().await.unwrap();
+            let value = db.dbis.read_trivial_table_tx(&tx, &key).await.unwrap();
+            assert_eq!(value.map(|v| (v.a, v.b)), Some((3, 4)));
+            let value = db
+                .dbis
+                .read_legacy_trivial_table_tx(&tx, &key)
+                .await
+                .unwrap();
+            assert_eq!(value.map(|v| (v.a, v.b)), Some((5, 6)));
+            assert!(
+                table_exists_tx(&tx, legacy::TrivialTable::NAME)
+                    .await
+                    .unwrap()
+            );
+            assert_eq!(legacy::TrivialTable::NAME, Some("LegacyTrivialTable"));
+        }
+
+        #[derive(mdbx_derive::MDBXDatabase)]
+        #[mdbx(error = mdbx_derive::Error, database = DerivedDatabase)]
+        struct DerivedTables<M = mdbx_derive::mdbx::RW> {
+            #[table]
+            trivial: mdbx_derive::TableHandle<TrivialTable, M>,
+            #[table(read_only)]
+            shared: mdbx_derive::TableHandle<TrivialTable2, mdbx_derive::mdbx::RO>,
+            #[table(optional)]
+            renamed: Option<mdbx_derive::TableHandle<RenamedTable, M>>,
+            #[table(read_only, flags = REVERSE_DUP)]
+            dups: mdbx_derive::TableHandle<TrivialDupTable, mdbx_derive::mdbx::RO>,
+        }
+
+        #[derive(mdbx_derive::MDBXDatabase)]
+        #[mdbx(database = FlaggedDatabase)]
+        struct FlaggedTables<M = mdbx_derive::mdbx::RW> {
+            #[table(flags = REVERSE_DUP)]
+            dups: mdbx_derive::TableHandle<TrivialDupTable, M>,
+        }
+
+        #[tokio::test]
+        async fn derived_table_flags() {
+            use mdbx_derive::{MDBXTable, mdbx::DatabaseFlags};
+
+            for (dup_flags, matches) in [
+                (DatabaseFlags::DUP_SORT | DatabaseFlags::REVERSE_DUP, true),
+                (DatabaseFlags::DUP_SORT, false),
+            ] {
+                let env = temp_env("derived_table_flags");
+                let tx = env.begin_rw_txn().await.unwrap();
+                TrivialTable2::create_table_tx(&tx, TrivialTable2::FLAGS)
+                    .await
+                    .unwrap();
+                tx.create_db(TrivialDupTable::NAME, dup_flags).await.unwrap();
+                tx.commit().await.unwrap();
+
+                let result = DerivedTables::new(&env).await;
+                if matches {
+                    let dbis = result.unwrap();
+                    assert!(dbis.renamed.is_none());
+                } else {
+                    let err = result.unwrap_err();
+                    assert!(
+                        matches!(
+                            &err,
+                            mdbx_derive::Error::FlagsMismatch { table, expected, .. }
+                                if table == "TrivialDupTable"
+                                    && *expected == (DatabaseFlags::DUP_SORT
+                                        | DatabaseFlags::REVERSE_DUP).bits()
+                        ),
+                        "{err:?}"
+                    );
+                }
+            }
+        }
+
+        #[tokio::test]
+        async fn derived_dbis() {
+            use mdbx_derive::{HasMDBXTableDbis, MDBXTable, mdbx::DatabaseFlags};
+
+            let env = temp_env("derived_dbis");
+            let tx = env.begin_rw_txn().await.unwrap();
+            TrivialTable2::create_table_tx(&tx, TrivialTable2::FLAGS)
+                .await
+                .unwrap();
+            tx.create_db(
+                TrivialDupTable::NAME,
+                DatabaseFlags::DUP_SORT | DatabaseFlags::REVERSE_DUP,
+            )
+            .await
+            .unwrap();
+            tx.commit().await.unwrap();
+
+            let key = TrivialKey { a: 1, b: 2 };
+            // The annotated struct holds the handles
+            let dbis = DerivedTables::new(&env).await.unwrap();
+            assert!(dbis.renamed.is_none());
+            let db = DerivedDatabase::new(env, dbis);
+            assert_eq!(db.dbis.trivial.dbi(), db.table::<TrivialTable, _>().dbi());
+            let tx = db.env.begin_rw_txn().await.unwrap();
+            db.dbis
+                .write_trivial_tx(&tx, &key, &TrivialObject { a: 1, b: 2 }, Default::default())
+                .await
+                .unwrap();
+            let stored = db.dbis.trivial.get(&tx, &key).await.unwrap().unwrap();
+            assert_eq!((stored.a, stored.b), (1, 2));
+            assert!(matches!(
+                db.dbis.read_renamed_tx(&tx, &1).await,
+                Err(mdbx_derive::Error::MissingTable("renamed"))
+            ));
+            // Read-only tables have read-only handles on a writable database too
+            let shared: mdbx_derive::TableHandle<TrivialTable2, mdbx_derive::mdbx::RO> =
+                db.dbis.shared;
+            assert!(shared.get(&tx, &key).await.unwrap().is_none());
+            let shared: mdbx_derive::TableHandle<TrivialTable2, mdbx_derive::mdbx::RO> =
+                db.table::<TrivialTable2, _>();
+            assert_eq!(shared.dbi(), db.dbis.shared().dbi());
+            assert!(db.get::<TrivialTable2, _>(&tx, &key).await.unwrap().is_none());
+            assert!(db.dbis.read_shared_tx(&tx, &key).await.unwrap().is_none());
+        }
+
+        #[tokio::test]
+        async fn derived_owned_flags() {
+            use mdbx_derive::{MDBXDatabase, MDBXDupSortTable, MDBXTable, mdbx::DatabaseFlags, schema};
+
+            let env = temp_env("derived_owned_flags");
+            let dbis = FlaggedTables::new(&env).await.unwrap();
+            let db = FlaggedDatabase::new(env.clone(), dbis);
+            let flags = DatabaseFlags::DUP_SORT | DatabaseFlags::REVERSE_DUP;
+            let tx = db.env.begin_rw_txn().await.unwrap();
+            let created = schema::dbi_flags_tx(&tx, db.dbis.dups.dbi()).unwrap();
+            assert_eq!(created.map(|f| f.bits()), Some(flags.bits()));
+            // Duplicates are compared from their last byte, the batch write can't append them
+            // in bytewise order
+            let key = TrivialKey { a: 1, b: 1 };
+            let items = [(&key, Raw(1)), (&key, Raw(256))];
+            db.dbis.dups.put_many(&tx, items, Default::default()).await.unwrap();
+            let dups = TrivialDupTable::get_dups_tx(&tx, Some(db.dbis.dups.dbi()), &key)
+                .await
+                .unwrap();
+            assert_eq!(dups.iter().map(|v| v.0).collect::<Vec<_>>(), [256, 1]);
+            tx.commit().await.unwrap();
+
+            // The fingerprint covers the flags
+            let fingerprints = <FlaggedDatabase>::schema_fingerprints();
+            assert_ne!(
+                fingerprints["TrivialDupTable"],
+                schema::table_fingerprint::<TrivialDupTable>()
+            );
+            db.write_schema_fingerprints().await.unwrap();
+            db.check_schema().await.unwrap();
+            FlaggedTables::new(&env).await.unwrap();
+
+            let env = temp_env("derived_owned_flags_mismatch");
+            let tx = env.begin_rw_txn().await.unwrap();
+            TrivialDupTable::create_table_tx(&tx, TrivialDupTable::FLAGS)
+                .await
+                .unwrap();
+            tx.commit().await.unwrap();
+            let err = FlaggedTables::new(&env).await.unwrap_err();
+            assert!(
+                matches!(
+                    &err,
+                    mdbx_derive::Error::FlagsMismatch { table, expected, .. }
+                        if table == "TrivialDupTable" && *expected == flags.bits()
+                ),
+                "{err:?}"
+            );
+        }
+
+        #[tokio::test]
+        async fn derived_txn_tables() {
+            use mdbx_derive::{MDBXDatabase, MDBXTable, mdbx::DatabaseFlags};
+
+            let env = temp_env("derived_txn_tables");
+            let tx = env.begin_rw_txn().await.unwrap();
+            TrivialTable2::create_table_tx(&tx, TrivialTable2::FLAGS)
+                .await
+                .unwrap();
+            tx.create_db(
+                TrivialDupTable::NAME,
+                DatabaseFlags::DUP_SORT | DatabaseFlags::REVERSE_DUP,
+            )
+            .await
+            .unwrap();
+            tx.commit().await.unwrap();
+
+            let key = TrivialKey { a: 1, b: 2 };
+            let dbis = DerivedTables::new(&env).await.unwrap();
+            let db = DerivedDatabase::new(env.clone(), dbis);
+            let txn = db.begin_rw().await.unwrap();
+            txn.trivial()
+                .put(&key, &TrivialObject { a: 1, b: 2 }, Default::default())
+                .await
+                .unwrap();
+            // The optional table didn't exist when the database opened
+            assert!(txn.renamed().is_none());
+            assert!(txn.optional_table::<RenamedTable, _>().is_none());
+            // Read-only tables are read-only in a writable transaction too
+            let shared: mdbx_derive::TxnTable<
+                '_,
+                TrivialTable2,
+                mdbx_derive::mdbx::RW,
+                mdbx_derive::mdbx::RO,
+            > = txn.shared();
+            assert!(shared.get(&key).await.unwrap().is_none());
+            assert_eq!(txn.table::<TrivialTable, _>().dbi(), db.dbis.trivial.dbi());
+            txn.commit().await.unwrap();
+
+            RenamedTable::create_table(&env, RenamedTable::FLAGS)
+                .await
+                .unwrap();
+            let dbis = DerivedTables::new(&env).await.unwrap();
+            let db = DerivedDatabase::new(env, dbis);
+            let txn = db.begin_rw().await.unwrap();
+            let renamed = txn.renamed().unwrap();
+            assert_eq!(Some(renamed.dbi()), db.dbis.renamed.map(|t| t.dbi()));
+            renamed.put(&1, &Raw(7), Default::default()).await.unwrap();
+            let stored = txn.optional_table::<RenamedTable, _>().unwrap().get(&1).await;
+            assert_eq!(stored.unwrap().map(|v| v.0), Some(7));
+            let stored = txn.trivial().get(&key).await.unwrap().unwrap();
+            assert_eq!((stored.a, stored.b), (1, 2));
+        }
+
+        const MIGRATIONS: &[mdbx_derive::Migration<MigratedDatabaseDbi, mdbx_derive::Error>] =
+            &[mdbx_derive::Migration::new(1, |tx, dbis| {
+                Box::pin(async move {
+                    dbis.write_trivial_table_tx(
+                        tx,
+                        &TrivialKey { a: 1, b: 2 },
+                        &TrivialObject { a: 3, b: 4 },
+                        mdbx_derive::mdbx::WriteFlags::default(),
+                    )
+                    .await
+                })
+            })];
+
+        mdbx_database!(
+            MigratedDatabase,
+            mdbx_derive::Error,
+            (),
+            TrivialTable;
+            migrations = MIGRATIONS
+        );
+
+        #[tokio::test]
+        async fn open_runs_migrations() {
+            use mdbx_derive::{MDBXDatabase, MDBXTable};
+
+            let path = temp_path("open_runs_migrations");
+            let url = path.to_str().unwrap();
+            let db = <MigratedDatabase>::open_create_tables_with_defaults(url, builder())
+                .await
+                .unwrap();
+            assert_eq!(db.schema_version().await.unwrap(), 1);
+            let key = TrivialKey { a: 1, b: 2 };
+            let value = TrivialTable::get_item(&db, &key).await.unwrap();
+            assert_eq!(value.map(|v| (v.a, v.b)), Some((3, 4)));
+            TrivialTable::clear(&db).await.unwrap();
+            drop(db);
+
+            // Applied migrations don't run again on the next open
+            let db = <MigratedDatabase>::open_create_tables_with_defaults(url, builder())
+                .await
+                .unwrap();
+            assert_eq!(db.schema_version().await.unwrap(), 1);
+            assert!(TrivialTable::get_item(&db, &key).await.unwrap().is_none());
+        }
+
+        #[tokio::test]
+        async fn migrations_roll_back() {
+            use mdbx_derive::{MDBXTable, Migration, migration::run_migrations};
+            use std::sync::atomic::{AtomicU64, Ordering};
+
+            type Migrations = [Migration<MigratedDatabaseDbi, mdbx_derive::Error>];
+            // Every run of a migration writes the key numbered by the run
+            static RUNS: AtomicU64 = AtomicU64::new(0);
+            fn key(run: u64) -> TrivialKey {
+                TrivialKey { a: 1, b: run }
+            }
+            fn up(version: u64) -> Migration<MigratedDatabaseDbi, mdbx_derive::Error> {
+                Migration::new(version, |tx, dbis| {
+                    Box::pin(async move {
+                        let run = RUNS.fetch_add(1, Ordering::SeqCst);
+                        let value = TrivialObject { a: 0, b: 0 };
+                        dbis.write_trivial_table_tx(tx, &key(run), &value, Default::default())
+                            .await
+                    })
+                })
+            }
+            let failing = Migration::new(2, |_, _| {
+                Box::pin(async { Err(mdbx_derive::Error::IncorrectSchema(vec![])) })
+            });
+
+            let env = temp_env("migrations_roll_back");
+            let dbis = MigratedDatabaseDbi::new(&env).await.unwrap();
+            let schema_version = async || {
+                let tx = env.begin_ro_txn().await.unwrap();
+                mdbx_derive::migration::schema_version_tx(&tx).await.unwrap()
+            };
+
+            // The first migration is rolled back with the failing one
+            let migrations: &Migrations = &[up(1), failing];
+            assert!(run_migrations(&env, &dbis, migrations).await.is_err());
+            assert_eq!(schema_version().await, 0);
+            assert!(TrivialTable::get_item(&env, &key(0)).await.unwrap().is_none());
+
+            let migrations: &Migrations = &[up(1), up(2)];
+            assert_eq!(run_migrations(&env, &dbis, migrations).await.unwrap(), 2);
+            assert_eq!(schema_version().await, 2);
+            let runs = RUNS.load(Ordering::SeqCst);
+            assert_eq!(runs, 3);
+            for run in 1..runs {
+                assert!(TrivialTable::get_item(&env, &key(run)).await.unwrap().is_some());
+            }
+
+            // Everything is applied already
+            assert_eq!(run_migrations(&env, &dbis, migrations).await.unwrap(), 2);
+            assert_eq!(RUNS.load(Ordering::SeqCst), runs);
+        }
+
+        #[tokio::test]
+        async fn write_txn_put() {
+            use mdbx_derive::{MDBXTable, WriteTxn};
+
+            let env = temp_env("write_txn_put");
+            let tx = WriteTxn::begin(&env).await.unwrap();
+            TrivialTable::put_item_tx(
+                &tx,
+                None,
+                &TrivialKey { a: 1, b: 2 },
+                &TrivialObject { a: 3, b: 4 },
+                Default::default(),
+            )
+            .await
+            .unwrap();
+            tx.commit().await.unwrap();
+
+            let value = TrivialTable::get_item(&env, &TrivialKey { a: 1, b: 2 })
+                .await
+                .unwrap();
+            assert_eq!(value.map(|v| (v.a, v.b)), Some((3, 4)));
+
+            // An aborted transaction leaves nothing behind
+            let tx = WriteTxn::begin(&env).await.unwrap();
+            TrivialTable::put_item_tx(
+                &tx,
+                None,
+                &TrivialKey { a: 5, b: 6 },
+                &TrivialObject { a: 7, b: 8 },
+                Default::default(),
+            )
+            .await
+            .unwrap();
+            tx.abort();
+            assert!(
+                TrivialTable::get_item(&env, &TrivialKey { a: 5, b: 6 })
+                    .await
+                    .unwrap()
+                    .is_none()
+            );
+        }
+
+        #[tokio::test]
+        async fn retry_map_full() {
+            use mdbx_derive::{MDBXDatabase, MDBXTable, RetryPolicy, futures::future::BoxFuture};
+
+            fn write(
+                tx: &mdbx_derive::mdbx::TransactionAny<mdbx_derive::mdbx::RW>,
+                count: u64,
+            ) -> BoxFuture<'_, Result<(), mdbx_derive::Error>> {
+                Box::pin(async move {
+                    let items = (0..count).map(|a| (TrivialKey { a, b: 0 }, TrivialObject { a, b: a }));
+                    TrivialTable::put_many_tx(tx, None, items, Default::default()).await?;
+                    Ok(())
+                })
+            }
+
+            let mut builder = builder();
+            builder.set_geometry(mdbx_derive::mdbx::Geometry {
+                size: Some(0..(1 << 20)),
+                growth_step: Some(1 << 16),
+                shrink_threshold: None,
+                page_size: None,
+            });
+            let env = mdbx_derive::mdbx::EnvironmentAny::open_local(&temp_path("retry_map_full"), builder)
+                .unwrap();
+            let dbis = TrivialDatabaseDbi::new(&env).await.unwrap();
+            let db = TrivialDatabase::new(env, dbis);
+
+            // Whether or not the map could grow, the write ends with its own result
+            let grown = match db.write_with_retry(RetryPolicy::transient(4), |tx| write(tx, 50_000)).await {
+                Ok(()) => true,
+                Err(e) => {
+                    assert!(e.is_map_full(), "{e:?}");
+                    false
+                }
+            };
+            let key = TrivialKey { a: 49_999, b: 0 };
+            assert_eq!(TrivialTable::get_item(&db, &key).await.unwrap().is_some(), grown);
+
+            db.write_with_retry(RetryPolicy::transient(4), |tx| write(tx, 100)).await.unwrap();
+            let key = TrivialKey { a: 99, b: 0 };
+            assert!(TrivialTable::get_item(&db, &key).await.unwrap().is_some());
+        }
+
+        #[tokio::test]
+        async fn write_and_read() {
+            use mdbx_derive::{MDBXDatabase, MDBXTable, RetryPolicy};
+
+            let env = temp_env("write_and_read");
+            let dbis = TrivialDatabaseDbi::new(&env).await.unwrap();
+            let db = TrivialDatabase::new(env, dbis);
+            let dbi = db.dbis.trivial_table;
+            db.write_with_retry(RetryPolicy::transient(3), |tx| {
+                Box::pin(async move {
+                    let key = TrivialKey { a: 1, b: 2 };
+                    TrivialTable::put_item_tx(
+                        tx,
+                        Some(dbi),
+                        &key,
+                        &TrivialObject { a: 3, b: 4 },
+                        Default::default(),
+                    )
+                    .await
+                })
+            })
+            .await
+            .unwrap();
+            let value = db
+                .read(|tx| {
+                    Box::pin(async move {
+                        TrivialTable::get_item_tx(tx, Some(dbi), &TrivialKey { a: 1, b: 2 }).await
+                    })
+                })
+                .await
+                .unwrap();
+            assert_eq!(value.map(|v| (v.a, v.b)), Some((3, 4)));
+
+            // A failing closure aborts the transaction with everything it wrote
+            let result: Result<(), _> = db
+                .write(|tx| {
+                    Box::pin(async move {
+                        let key = TrivialKey { a: 5, b: 6 };
+                        TrivialTable::put_item_tx(
+                            tx,
+                            Some(dbi),
+                            &key,
+                            &TrivialObject { a: 7, b: 8 },
+                            Default::default(),
+                        )
+                        .await?;
+                        Err(mdbx_derive::Error::MissingTable("rollback"))
+                    })
+                })
+                .await;
+            assert!(result.is_err());
+            let key = TrivialKey { a: 5, b: 6 };
+            assert!(TrivialTable::get_item(&db, &key).await.unwrap().is_none());
+        }
+
+        #[tokio::test]
+        async fn dbi_cache_per_env() {
+            use mdbx_derive::{CachedEnvironment, HasMDBXEnvironment, MDBXTable};
+
+            let env = temp_env("dbi_cache_per_env");
+            let key = TrivialKey { a: 1, b: 2 };
+            let value = TrivialObject { a: 3, b: 4 };
+            assert_eq!(env.cached_dbi::<TrivialTable>(), None);
+            TrivialTable::put_item(&env, &key, &value, Default::default())
+                .await
+                .unwrap();
+            // Every handle of the environment shares the cache filled by the first open
+            let dbi = env.cached_dbi::<TrivialTable>().unwrap();
+            assert_eq!(env.clone().cached_dbi::<TrivialTable>(), Some(dbi));
+            let cached = CachedEnvironment::new(env.clone());
+            assert_eq!(cached.cached_dbi::<TrivialTable>(), Some(dbi));
+            let out = TrivialTable::get_item(&cached, &key).await.unwrap().unwrap();
+            assert_eq!((out.a, out.b), (3, 4));
+
+            let dbis = TrivialDatabaseDbi::new(&env).await.unwrap();
+            let db = TrivialDatabase::new(env.clone(), dbis);
+            assert_eq!(db.dbis.trivial_table, dbi);
+            assert_eq!(db.cached_dbi::<TrivialTable2>(), None);
+            let out = TrivialTable::get_item(&db, &key).await.unwrap().unwrap();
+            assert_eq!((out.a, out.b), (3, 4));
+
+            // Dropping through one handle is seen by the others
+            // SAFETY: no DBI of the table is used afterwards
+            unsafe { TrivialTable::drop_table(&cached) }.await.unwrap();
+            assert_eq!(env.cached_dbi::<TrivialTable>(), None);
+            assert_eq!(db.cached_dbi::<TrivialTable>(), None);
+
+            let other = temp_env("dbi_cache_per_env_other");
+            assert_eq!(other.cached_dbi::<TrivialTable>(), None);
+        }
+
+        #[tokio::test]
+        async fn batch_reads_and_deletes() {
+            use mdbx_derive::{MDBXTable, WriteTxn};
+
+            let env = temp_env("batch_reads_and_deletes");
+            let dbis = TrivialDatabaseDbi::new(&env).await.unwrap();
+            let db = TrivialDatabase::new(env, dbis);
+            let keys: Vec<_> = (0..4).map(|a| TrivialKey { a, b: 0 }).collect();
+            let written = TrivialTable::put_many(
+                &db,
+                keys.iter().map(|k| (k, TrivialObject { a: k.a, b: 0 })),
+                Default::default(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(written, 4);
+
+            // Missing keys read as `None` in their position
+            let lookup = [
+                TrivialKey { a: 2, b: 0 },
+                TrivialKey { a: 9, b: 0 },
+                TrivialKey { a: 0, b: 0 },
+            ];
+            let values = TrivialTable::get_many(&db, &lookup).await.unwrap();
+            assert_eq!(
+                values
+                    .iter()
+                    .map(|v| v.as_ref().map(|v| v.a))
+                    .collect::<Vec<_>>(),
+                [Some(2), None, Some(0)]
+            );
+
+            let tx = WriteTxn::begin(&db.env).await.unwrap();
+            let values = db
+                .dbis
+                .read_many_trivial_table_tx(&tx, &keys)
+                .await
+                .unwrap();
+            assert!(values.iter().all(Option::is_some));
+            let deleted = db
+                .dbis
+                .del_many_trivial_table_tx(&tx, &lookup)
+                .await
+                .unwrap();
+            assert_eq!(deleted, 2);
+            tx.commit().await.unwrap();
+
+            let values = TrivialTable::get_many(&db, &keys).await.unwrap();
+            assert_eq!(
+                values
+                    .iter()
+                    .map(|v| v.as_ref().map(|v| v.a))
+                    .collect::<Vec<_>>(),
+                [None, Some(1), None, Some(3)]
+            );
+        }
+
+        #[tokio::test]
+        async fn stats_and_clear() {
+            use mdbx_derive::{MDBXDatabase, MDBXTable};
+
+            let env = temp_env("stats_and_clear");
+            let dbis = TrivialDatabase2Dbi::new(&env).await.unwrap();
+            let db = TrivialDatabase2::new(env, dbis);
+            let items = (0..10).map(|a| (TrivialKey { a, b: 0 }, TrivialObject { a, b: 0 }));
+            TrivialTable::put_many(&db, items.clone(), Default::default())
+                .await
+                .unwrap();
+            TrivialTable2::put_many(&db, items.take(3), Default::default())
+                .await
+                .unwrap();
+
+            let stats = db.stats_all().await.unwrap();
+            assert_eq!(stats.len(), 2);
+            assert_eq!(stats["TrivialTable"].entries, 10);
+            assert_eq!(stats["TrivialTable2"].entries, 3);
+            assert!(stats["TrivialTable"].bytes() > 0);
+
+            // Clearing a table keeps it and leaves the others alone
+            TrivialTable::clear(&db).await.unwrap();
+            assert_eq!(TrivialTable::stat(&db).await.unwrap().entries, 0);
+            assert_eq!(TrivialTable2::stat(&db).await.unwrap().entries, 3);
+
+            db.clear_all().await.unwrap();
+            let stats = db.stats_all().await.unwrap();
+            assert!(stats.values().all(|s| s.entries == 0), "{stats:?}");
+        }
+
+        #[tokio::test]
+        async fn create_checks_schema() {
+            use mdbx_derive::schema::{Fingerprints, stored_fingerprints_tx, write_fingerprints_tx};
+
+            let path = temp_path("create_checks_schema");
+            let url = path.to_str().unwrap();
+            let db = <TrivialDatabase>::open_create_tables_with_defaults(url, builder())
+                .await
+                .unwrap();
+            drop(db);
+            // Reopening a matching database rewrites the same fingerprints
+            let db = <TrivialDatabase>::open_create_tables_with_defaults(url, builder())
+                .await
+                .unwrap();
+            let tx = db.env.begin_rw_txn().await.unwrap();
+            let stale = Fingerprints::from([("TrivialTable".to_string(), 0)]);
+            write_fingerprints_tx(&tx, &stale).await.unwrap();
+            tx.commit().await.unwrap();
+            drop(db);
+
+            let err = <TrivialDatabase>::open_create_tables_with_defaults(url, builder())
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(&err, mdbx_derive::Error::SchemaMismatch(t) if t == &["TrivialTable"]),
+                "{err:?}"
+            );
+            let env = mdbx_derive::mdbx::EnvironmentAny::open_local(&path, builder()).unwrap();
+            let tx = env.begin_ro_txn().await.unwrap();
+            assert_eq!(stored_fingerprints_tx(&tx).await.unwrap(), Some(stale));
+        }
+
+        #[tokio::test]
+        async fn read_only_open() {
+            use mdbx_derive::{HasMDBXEnvironment, HasMDBXTableDbis, MDBXDatabase};
+
+            let path = temp_path("read_only_open");
+            let url = path.to_str().unwrap();
+            let db = <TrivialDatabase>::open_create_tables_with_defaults(url, builder())
+                .await
+                .unwrap();
+            let key = TrivialKey { a: 1, b: 2 };
+            let tx = db.begin_rw().await.unwrap();
+            db.put::<TrivialTable, _>(&tx, &key, &TrivialObject { a: 3, b: 4 }, Default::default())
+                .await
+                .unwrap();
+            tx.commit().await.unwrap();
+            drop(db);
+
+            // A writable builder still opens the environment read-only
+            let db = TrivialDatabase::<mdbx_derive::mdbx::RO>::open_tables_read_only_with_defaults(
+                url,
+                builder(),
+            )
+            .await
+            .unwrap();
+            assert!(db.env().is_read_only().await.unwrap());
+            assert!(db.env().begin_rw_txn().await.is_err());
+            let tx = db.begin_ro().await.unwrap();
+            let value = db.get::<TrivialTable, _>(&tx, &key).await.unwrap().unwrap();
+            assert_eq!((value.a, value.b), (3, 4));
+        }
+
+        #[tokio::test]
+        async fn savepoints() {
+            use mdbx_derive::MDBXDatabase;
+
+            let env = temp_env("savepoints");
+            let dbis = TrivialDatabase2Dbi::new(&env).await.unwrap();
+            let db = TrivialDatabase2::new(env, dbis);
+            let rolled_back = TrivialKey { a: 1, b: 1 };
+            let committed = TrivialKey { a: 2, b: 2 };
+            let value = TrivialObject { a: 3, b: 4 };
+
+            let mut txn = db.begin_rw().await.unwrap();
+            let sp = txn.savepoint().await.unwrap();
+            sp.trivial_table()
+                .put(&rolled_back, &value, Default::default())
+                .await
+                .unwrap();
+            assert!(sp.trivial_table().get(&rolled_back).await.unwrap().is_some());
+            sp.rollback();
+            let sp = txn.savepoint().await.unwrap();
+            sp.trivial_table()
+                .put(&committed, &value, Default::default())
+                .await
+                .unwrap();
+            sp.commit().await.unwrap();
+            assert!(txn.trivial_table().get(&rolled_back).await.unwrap().is_none());
+            txn.commit().await.unwrap();
+
+            let txn = db.begin_ro().await.unwrap();
+            assert!(txn.trivial_table().get(&rolled_back).await.unwrap().is_none());
+            let stored = txn.trivial_table().get(&committed).await.unwrap().unwrap();
+            assert_eq!((stored.a, stored.b), (3, 4));
+        }
+
+        mdbx_database!(MetadataDatabase, mdbx_derive::Error, Raw<u64>, TrivialTable);
+
+        #[tokio::test]
+        async fn legacy_metadata() {
+            use mdbx_derive::{DATABASE_METADATA_NAME, MDBXDatabase, TableObjectEncode};
+
+            // Older versions kept the metadata in the main DB
+            let env = temp_env("legacy_metadata");
+            let tx = env.begin_rw_txn().await.unwrap();
+            let main = tx.open_db(None).await.unwrap().dbi();
+            let legacy = Raw(7u64).table_encode().unwrap();
+            tx.put(main, b"metadata", &legacy, Default::default())
+                .await
+                .unwrap();
+            tx.commit().await.unwrap();
+
+            let dbis = MetadataDatabaseDbi::new(&env).await.unwrap();
+            let db = MetadataDatabase::new(env, dbis);
+            assert_eq!(db.metadata().await.unwrap().map(|m| m.0), Some(7));
+            assert!(db.migrate_metadata().await.unwrap());
+            assert!(!db.migrate_metadata().await.unwrap());
+            assert_eq!(db.metadata().await.unwrap().map(|m| m.0), Some(7));
+
+            let tx = db.env.begin_ro_txn().await.unwrap();
+            let main = tx.open_db(None).await.unwrap().dbi();
+            assert!(tx.get::<()>(main, b"metadata").await.unwrap().is_none());
+            let dbi = tx.open_db(Some(DATABASE_METADATA_NAME)).await.unwrap().dbi();
+            assert_eq!(
+                tx.get::<Vec<u8>>(dbi, b"metadata").await.unwrap(),
+                Some(legacy)
+            );
+        }
+
+        #[tokio::test]
+        async fn metadata_named_table() {
+            use mdbx_derive::{MDBXDatabase, mdbx::DatabaseFlags, schema::is_table_tx};
+
+            // A table under the legacy metadata key is not metadata
+            let env = temp_env("metadata_named_table");
+            let tx = env.begin_rw_txn().await.unwrap();
+            tx.create_db(Some("metadata"), DatabaseFlags::empty())
+                .await
+                .unwrap();
+            assert_eq!(is_table_tx(&tx, b"metadata").unwrap(), Some(true));
+            assert_eq!(is_table_tx(&tx, b"other").unwrap(), Some(false));
+            tx.commit().await.unwrap();
+
+            let dbis = MetadataDatabaseDbi::new(&env).await.unwrap();
+            let db = MetadataDatabase::new(env, dbis);
+            assert!(db.metadata().await.unwrap().is_none());
+            assert!(!db.migrate_metadata().await.unwrap());
+            let tx = db.env.begin_ro_txn().await.unwrap();
+            assert_eq!(is_table_tx(&tx, b"metadata").unwrap(), Some(true));
+        }
+
+        #[tokio::test]
+        async fn drop_tables() {
+            use mdbx_derive::{MDBXDatabase, MDBXTable, schema::table_exists_tx};
+
+            let env = temp_env("drop_tables");
+            let dbis = TrivialDatabase2Dbi::new(&env).await.unwrap();
+            let db = TrivialDatabase2::new(env, dbis);
+            let key = TrivialKey { a: 1, b: 2 };
+            TrivialTable2::put_item(&db, &key, &TrivialObject { a: 3, b: 4 }, Default::default())
+                .await
+                .unwrap();
+            // SAFETY: `db` has no clones and no handle of it was kept
+            let env = unsafe { db.drop_all() }.await.unwrap();
+            let tx = env.begin_ro_txn().await.unwrap();
+            for name in [TrivialTable::NAME, TrivialTable2::NAME] {
+                assert!(!table_exists_tx(&tx, name).await.unwrap());
+            }
+            drop(tx);
+
+            // Opening the database again creates empty tables
+            let dbis = TrivialDatabase2Dbi::new(&env).await.unwrap();
+            let db = TrivialDatabase2::new(env.clone(), dbis);
+            assert!(TrivialTable2::get_item(&db, &key).await.unwrap().is_none());
+            drop(db);
+
+            // SAFETY: the database above was dropped, `env` has no other handle of the table
+            unsafe { TrivialTable2::drop_table(&env) }.await.unwrap();
+            // Dropping a missing table does nothing
+            unsafe { TrivialTable2::drop_table(&env) }.await.unwrap();
+            let tx = env.begin_ro_txn().await.unwrap();
+            assert!(!table_exists_tx(&tx, TrivialTable2::NAME).await.unwrap());
+            assert!(table_exists_tx(&tx, TrivialTable::NAME).await.unwrap());
+        }
+
+        mdbx_table_def!(MetaTable, TrivialKey, TrivialObject, mdbx_derive::Error, Raw<u64>);
+        mdbx_table_def!(MetaTable2, TrivialKey, TrivialObject, mdbx_derive::Error, Raw<u64>);
+
+        #[tokio::test]
+        async fn table_metadata() {
+            use mdbx_derive::{MDBXTable, schema::table_exists_tx};
+
+            let env = temp_env("table_metadata");
+            assert!(MetaTable::table_metadata(&env).await.unwrap().is_none());
+            MetaTable::write_table_metadata(&env, &Raw(1))
+                .await
+                .unwrap();
+            MetaTable2::write_table_metadata(&env, &Raw(2))
+                .await
+                .unwrap();
+            MetaTable::write_table_metadata(&env, &Raw(3))
+                .await
+                .unwrap();
+            // Each table keeps its own metadata, the last write wins
+            assert_eq!(
+                MetaTable::table_metadata(&env).await.unwrap().map(|m| m.0),
+                Some(3)
+            );
+            assert_eq!(
+                MetaTable2::table_metadata(&env).await.unwrap().map(|m| m.0),
+                Some(2)
+            );
+            // Writing metadata doesn't create the table itself
+            let tx = env.begin_ro_txn().await.unwrap();
+            assert!(!table_exists_tx(&tx, MetaTable::NAME).await.unwrap());
+            drop(tx);
+
+            // Dropping a table drops its metadata
+            MetaTable::put_item(
+                &env,
+                &TrivialKey { a: 1, b: 2 },
+                &TrivialObject { a: 3, b: 4 },
+                Default::default(),
+            )
+            .await
+            .unwrap();
+            // SAFETY: no DBI of the table is kept past this point
+            unsafe { MetaTable::drop_table(&env) }.await.unwrap();
+            assert!(MetaTable::table_metadata(&env).await.unwrap().is_none());
+            assert_eq!(
+                MetaTable2::table_metadata(&env).await.unwrap().map(|m| m.0),
+                Some(2)
+            );
+        }
+
+        #[tokio::test]
+        async fn metadata_round_trip() {
+            use mdbx_derive::{DATABASE_METADATA_NAME, MDBXDatabase, schema::table_exists_tx};
+
+            let env = temp_env("metadata_round_trip");
+            let dbis = MetadataDatabaseDbi::new(&env).await.unwrap();
+            let db = MetadataDatabase::new(env.clone(), dbis);
+            assert!(db.metadata().await.unwrap().is_none());
+            // Nothing to migrate in a fresh environment
+            assert!(!db.migrate_metadata().await.unwrap());
+            db.write_metadata(&Raw(5)).await.unwrap();
+            db.write_metadata(&Raw(6)).await.unwrap();
+            assert_eq!(db.metadata().await.unwrap().map(|m| m.0), Some(6));
+            drop(db);
+
+            let tx = env.begin_ro_txn().await.unwrap();
+            assert!(
+                table_exists_tx(&tx, Some(DATABASE_METADATA_NAME))
+                    .await
+                    .unwrap()
+            );
+            drop(tx);
+            let dbis = MetadataDatabaseDbi::new(&env).await.unwrap();
+            let db = MetadataDatabase::new(env, dbis);
+            assert_eq!(db.metadata().await.unwrap().map(|m| m.0), Some(6));
+        }
+
+        #[tokio::test]
+        async fn schema_check() {
+            use mdbx_derive::MDBXDatabase;
+            use mdbx_derive::schema::{stored_fingerprints_tx, write_fingerprints_tx};
+
+            let env = temp_env("schema_check");
+            let dbis = TrivialDatabase2Dbi::new(&env).await.unwrap();
+            let db = TrivialDatabase2::new(env.clone(), dbis);
+            // Nothing stored yet, so any schema passes
+            db.check_schema().await.unwrap();
+            db.write_schema_fingerprints().await.unwrap();
+            db.check_schema().await.unwrap();
+            let tx = env.begin_ro_txn().await.unwrap();
+            let stored = stored_fingerprints_tx(&tx).await.unwrap().unwrap();
+            assert_eq!(stored, <TrivialDatabase2>::schema_fingerprints());
+            drop(tx);
+            assert_ne!(
+                <TrivialDatabase2>::schema_fingerprint(),
+                <TrivialDatabase>::schema_fingerprint()
+            );
+
+            // A database over a subset of the tables passes as well
+            let dbis = TrivialDatabaseDbi::new(&env).await.unwrap();
+            let single = TrivialDatabase::new(env.clone(), dbis);
+            single.check_schema().await.unwrap();
+
+            let mut stale = stored;
+            stale.insert("TrivialTable2".to_string(), 0);
+            let tx = env.begin_rw_txn().await.unwrap();
+            write_fingerprints_tx(&tx, &stale).await.unwrap();
+            tx.commit().await.unwrap();
+            let err = db.check_schema().await.unwrap_err();
+            assert!(
+                matches!(&err, mdbx_derive::Error::SchemaMismatch(t) if t == &["TrivialTable2"]),
+                "{err:?}"
+            );
+            single.check_schema().await.unwrap();
+        }
+
+        #[tokio::test]
+        async fn table_flags_checked() {
+            use mdbx_derive::{MDBXTable, schema::check_table_flags_tx};
+
+            let env = temp_env("table_flags_checked");
+            let dbis = TrivialDatabase2Dbi::new(&env).await.unwrap();
+            let tx = env.begin_ro_txn().await.unwrap();
+            let dbi = TrivialTable::open_table_tx(&tx).await.unwrap();
+            assert_eq!(dbi, dbis.trivial_table);
+            check_table_flags_tx::<TrivialTable, _>(&tx, dbi).unwrap();
+            assert_eq!(TrivialTable::open_table_checked_tx(&tx).await.unwrap(), dbi);
+            // The plain table doesn't have the flags of a DUP_SORT one
+            let err = check_table_flags_tx::<TrivialDupTable, _>(&tx, dbi).unwrap_err();
+            assert!(
+                matches!(&err, mdbx_derive::Error::FlagsMismatch { table, actual, .. } if table == "TrivialDupTable" && *actual == 0),
+                "{err:?}"
+            );
+        }
     }
 
     #[test]
@@ -82,6 +1887,110 @@ mod test {
         assert_eq!(ky.b, 24);
     }
 
+    #[test]
+    fn codec_postcard() {
+        let k = TrivialObject { a: 42, b: 24 };
+        let v = Zstd::<Postcard<TrivialObject>>::wrap(k);
+        let encoded = v.table_encode().expect("fail to encode");
+        assert_eq!(
+            encoded,
+            TrivialObject { a: 42, b: 24 }
+                .table_encode()
+                .expect("fail to encode")
+        );
+
+        let decoded = Zstd::<Postcard<TrivialObject>>::table_decode(&encoded)
+            .expect("fail to decode")
+            .into_object();
+        assert_eq!(decoded.a, 42);
+        assert_eq!(decoded.b, 24);
+
+        // Trailing bytes are rejected by both, which share a codec id
+        let mut trailing = postcard::to_allocvec(&TrivialObject { a: 42, b: 24 }).unwrap();
+        trailing.push(0);
+        let trailing = mdbx_derive::zstd::encode_all(Cursor::new(trailing), 1).unwrap();
+        assert!(Zstd::<Postcard<TrivialObject>>::table_decode(&trailing).is_err());
+        assert!(TrivialObject::table_decode(&trailing).is_err());
+    }
+
+    #[test]
+    fn fixed_size_object() {
+        use mdbx_derive::FixedSizeObject;
+
+        assert_eq!(<Raw<u64> as FixedSizeObject>::FIXED_SIZE, 8);
+        assert_eq!(
+            <TrivialKey as FixedSizeObject>::FIXED_SIZE,
+            std::mem::size_of::<u64>() * 2
+        );
+    }
+
+    #[test]
+    fn codec_id() {
+        assert_eq!(TrivialObject::codec_id(), "Zstd<Postcard>");
+        assert_eq!(
+            TrivialObject::codec_id(),
+            Zstd::<Postcard<TrivialObject>>::codec_id()
+        );
+        assert_eq!(
+            TrivialKey::codec_id(),
+            <Raw<TrivialKey> as TableObjectEncode>::codec_id()
+        );
+        assert_ne!(
+            Postcard::<u64>::codec_id(),
+            <Raw<u64> as TableObjectEncode>::codec_id()
+        );
+    }
+
+    #[test]
+    fn codec_raw() {
+        let v = Raw(42u64);
+        let encoded = v.table_encode().expect("fail to encode");
+        assert_eq!(encoded, 42u64.to_be_bytes());
+        assert_eq!(
+            *Raw::<u64>::table_decode(&encoded).expect("fail to decode"),
+            42
+        );
+    }
+
+    #[cfg(any(feature = "simd-json", feature = "serde_json"))]
+    #[test]
+    fn codec_json() {
+        let v = Zstd(mdbx_derive::Json(vec![1u64, 2, 3]));
+        let encoded = v.table_encode().expect("fail to encode");
+        let decoded =
+            Zstd::<mdbx_derive::Json<Vec<u64>>>::table_decode(&encoded).expect("fail to decode");
+        assert_eq!(decoded.into_object(), vec![1, 2, 3]);
+    }
+
+    #[cfg(any(feature = "simd-json", feature = "serde_json"))]
+    #[test]
+    fn codec_fallback() {
+        use mdbx_derive::{Fallback, Json, SniffedObject, tuple_list_type};
+
+        type Sniffed = Fallback<Zstd<Postcard<Vec<u64>>>, tuple_list_type!(Zstd<Json<Vec<u64>>>)>;
+
+        let legacy = Zstd(Json(vec![1u64, 2, 3]))
+            .table_encode()
+            .expect("fail to encode");
+        let decoded = Sniffed::table_decode(&legacy).expect("fail to decode");
+        assert_eq!(decoded.format(), 1);
+        assert!(decoded.is_legacy());
+        assert_eq!(*decoded, vec![1, 2, 3]);
+
+        let encoded = decoded.table_encode().expect("fail to encode");
+        assert_eq!(
+            encoded,
+            Zstd(Postcard(vec![1u64, 2, 3]))
+                .table_encode()
+                .expect("fail to encode")
+        );
+        let decoded = Sniffed::table_decode(&encoded).expect("fail to decode");
+        assert_eq!(decoded.format(), 0);
+        assert_eq!(decoded.into_object(), vec![1, 2, 3]);
+
+        assert!(Sniffed::table_decode(b"garbage").is_err());
+    }
+
     #[cfg(any(feature = "simd-json", feature = "serde_json"))]
     #[test]
     fn trivial_json() {
@@ -123,6 +2032,15 @@ mod test {
         assert_eq!(decoded.a, v.a);
     }
 
+    #[cfg(feature = "bcs")]
+    #[test]
+    fn test_codec_bcs() {
+        let v = ZstdBcsTest { a: 42 };
+        let ky = v.table_encode().unwrap();
+        let decoded = Zstd::<mdbx_derive::Bcs<ZstdBcsTest>>::table_decode(&ky).unwrap();
+        assert_eq!(decoded.object().a, v.a);
+    }
+
     #[cfg(feature = "bcs")]
     #[test]
     fn test_zstd_bcs() {
@@ -0,0 +1,105 @@
+use std::borrow::Borrow;
+
+use libmdbx_remote::{RW, TransactionAny, TransactionKind, WriteFlags};
+
+use crate::{
+    cursor::RawItem,
+    error::MDBXDeriveError,
+    key::KeyObjectEncode,
+    orm::MDBXTable,
+    schema::{CUSTOM_DUP_ORDER, CUSTOM_KEY_ORDER, dbi_flags_tx},
+    table::{TableObjectDecode, TableObjectEncode},
+};
+
+/// Items are encoded before any transaction call, so that the batch helpers do not keep
+/// the caller's iterator alive across awaits.
+pub fn encode_keys<T: MDBXTable>(
+    keys: impl IntoIterator<Item = impl Borrow<T::Key>>,
+) -> Result<Vec<Vec<u8>>, MDBXDeriveError> {
+    keys.into_iter().map(|k| k.borrow().key_encode()).collect()
+}
+
+pub fn encode_items<T: MDBXTable>(
+    items: impl IntoIterator<Item = (impl Borrow<T::Key>, impl Borrow<T::Value>)>,
+) -> Result<Vec<RawItem>, MDBXDeriveError> {
+    items
+        .into_iter()
+        .map(|(k, v)| Ok((k.borrow().key_encode()?, v.borrow().table_encode()?)))
+        .collect()
+}
+
+/// The remote protocol has no batch call, the lookups are issued together so that they
+/// share the round trip latency. Values are returned in the order of `keys`.
+pub async fn get_many_raw<T: MDBXTable, K: TransactionKind>(
+    tx: &TransactionAny<K>,
+    dbi: u32,
+    keys: Vec<Vec<u8>>,
+) -> Result<Vec<Option<T::Value>>, MDBXDeriveError> {
+    let values =
+        futures::future::try_join_all(keys.iter().map(|k| tx.get::<Vec<u8>>(dbi, k))).await?;
+    values
+        .into_iter()
+        .map(|v| v.map(|v| T::Value::table_decode(&v)).transpose())
+        .collect()
+}
+
+/// Writes the items in key order. The last value of a repeated key wins unless the table
+/// is DUPSORT. Writes stay sequential, as a pipelined APPEND could reach the server out of
+/// order.
+pub async fn put_many_raw<T: MDBXTable>(
+    tx: &TransactionAny<RW>,
+    dbi: u32,
+    mut items: Vec<RawItem>,
+    flags: WriteFlags,
+) -> Result<usize, MDBXDeriveError> {
+    if T::DUPSORT {
+        items.sort();
+        items.dedup();
+    } else {
+        items.reverse();
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        items.dedup_by(|a, b| a.0 == b.0);
+    }
+    let Some((first, _)) = items.first() else {
+        return Ok(0);
+    };
+
+    let mut cur = tx.cursor_with_dbi(dbi).await?;
+    // Appending skips the page search, it is only possible past the last key of the table.
+    // The items are sorted bytewise, so APPEND needs the default comparator, and APPEND_DUP
+    // the default one for duplicates too. A table may have been created with more flags than
+    // its definition, those of the DBI are used when known.
+    let table_flags = dbi_flags_tx(tx, dbi)?.unwrap_or(T::FLAGS);
+    let append = !table_flags.intersects(CUSTOM_KEY_ORDER)
+        && match cur.last::<Vec<u8>, ()>().await? {
+            Some((last, _)) => first > &last,
+            None => true,
+        };
+    let append_dup = T::DUPSORT && !table_flags.intersects(CUSTOM_DUP_ORDER);
+    let flags = match (append, append_dup) {
+        (true, true) => flags | WriteFlags::APPEND | WriteFlags::APPEND_DUP,
+        (true, false) => flags | WriteFlags::APPEND,
+        (false, _) => flags,
+    };
+    for (key, value) in &items {
+        cur.put(key, value, flags).await?;
+    }
+    Ok(items.len())
+}
+
+/// Deletes every given key, with all of its duplicates, and returns how many keys existed.
+pub async fn delete_many_raw(
+    tx: &TransactionAny<RW>,
+    dbi: u32,
+    mut keys: Vec<Vec<u8>>,
+) -> Result<usize, MDBXDeriveError> {
+    keys.sort();
+    keys.dedup();
+    let mut deleted = 0;
+    for key in &keys {
+        if tx.del(dbi, key, None).await? {
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
@@ -0,0 +1,387 @@
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    error::MDBXDeriveError,
+    key::{KeyObjectDecode, KeyObjectEncode},
+    table::{FixedSizeObject, TableObjectDecode, TableObjectEncode},
+};
+
+pub trait ObjectCodec: Sized {
+    type Object;
+
+    fn encode_object(object: &Self::Object) -> Result<Vec<u8>, MDBXDeriveError>;
+    fn decode_object(val: &[u8]) -> Result<Self::Object, MDBXDeriveError>;
+
+    fn wrap(object: Self::Object) -> Self;
+    fn into_object(self) -> Self::Object;
+    fn object(&self) -> &Self::Object;
+}
+
+macro_rules! impl_wrapper {
+    ( $( $name:ident )+ ) => {
+        $(
+            #[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            pub struct $name<T>(pub T);
+
+            impl<T> $name<T> {
+                pub fn into_inner(self) -> T {
+                    self.0
+                }
+            }
+
+            impl<T> From<T> for $name<T> {
+                fn from(value: T) -> Self {
+                    Self(value)
+                }
+            }
+
+            impl<T> Deref for $name<T> {
+                type Target = T;
+                fn deref(&self) -> &Self::Target {
+                    &self.0
+                }
+            }
+
+            impl<T> DerefMut for $name<T> {
+                fn deref_mut(&mut self) -> &mut Self::Target {
+                    &mut self.0
+                }
+            }
+
+            #[cfg(feature = "mdbx")]
+            impl<T> libmdbx_remote::TableObject for $name<T>
+            where
+                Self: TableObjectDecode,
+            {
+                fn decode(data_val: &[u8]) -> Result<Self, libmdbx_remote::Error> {
+                    <Self as TableObjectDecode>::table_decode(data_val)
+                        .map_err(|_| libmdbx_remote::Error::Corrupted)
+                }
+            }
+        )+
+    };
+}
+
+impl_wrapper! { Zstd Postcard Bcs Raw }
+#[cfg(any(feature = "serde_json", feature = "simd-json"))]
+impl_wrapper! { Json }
+
+macro_rules! impl_leaf_codec {
+    ( $( $name:ident [$($bound:tt)+] )+ ) => {
+        $(
+            impl<T: $($bound)+> ObjectCodec for $name<T> {
+                type Object = T;
+
+                fn encode_object(object: &Self::Object) -> Result<Vec<u8>, MDBXDeriveError> {
+                    Self::encode(object)
+                }
+
+                fn decode_object(val: &[u8]) -> Result<Self::Object, MDBXDeriveError> {
+                    Self::decode(val)
+                }
+
+                fn wrap(object: Self::Object) -> Self {
+                    Self(object)
+                }
+
+                fn into_object(self) -> Self::Object {
+                    self.0
+                }
+
+                fn object(&self) -> &Self::Object {
+                    &self.0
+                }
+            }
+
+            impl<T: $($bound)+> TableObjectEncode for $name<T> {
+                fn table_encode(&self) -> Result<Vec<u8>, MDBXDeriveError> {
+                    Self::encode(&self.0)
+                }
+
+                fn codec_id() -> Cow<'static, str> {
+                    Cow::Borrowed(stringify!($name))
+                }
+            }
+
+            impl<T: $($bound)+> TableObjectDecode for $name<T> {
+                fn table_decode(val: &[u8]) -> Result<Self, MDBXDeriveError> {
+                    Ok(Self(Self::decode(val)?))
+                }
+            }
+        )+
+    };
+}
+
+impl_leaf_codec! {
+    Postcard [Serialize + DeserializeOwned]
+    Bcs [Serialize + DeserializeOwned]
+    Raw [KeyObjectEncode + KeyObjectDecode]
+}
+#[cfg(any(feature = "serde_json", feature = "simd-json"))]
+impl_leaf_codec! { Json [Serialize + DeserializeOwned] }
+
+impl<T: Serialize + DeserializeOwned> Postcard<T> {
+    fn encode(object: &T) -> Result<Vec<u8>, MDBXDeriveError> {
+        Ok(postcard::to_allocvec(object)?)
+    }
+
+    fn decode(val: &[u8]) -> Result<T, MDBXDeriveError> {
+        // Trailing bytes are rejected so that a foreign format is not decoded by accident
+        let (object, remaining) = postcard::take_from_bytes(val)?;
+        if remaining.is_empty() {
+            Ok(object)
+        } else {
+            Err(MDBXDeriveError::IncorrectSchema(val.to_vec()))
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Bcs<T> {
+    fn encode(object: &T) -> Result<Vec<u8>, MDBXDeriveError> {
+        Ok(bcs::to_bytes(object)?)
+    }
+
+    fn decode(val: &[u8]) -> Result<T, MDBXDeriveError> {
+        Ok(bcs::from_bytes(val)?)
+    }
+}
+
+#[cfg(all(feature = "serde_json", not(feature = "simd-json")))]
+impl<T: Serialize + DeserializeOwned> Json<T> {
+    fn encode(object: &T) -> Result<Vec<u8>, MDBXDeriveError> {
+        Ok(serde_json::to_vec(object)?)
+    }
+
+    fn decode(val: &[u8]) -> Result<T, MDBXDeriveError> {
+        Ok(serde_json::from_slice(val)?)
+    }
+}
+
+#[cfg(feature = "simd-json")]
+impl<T: Serialize + DeserializeOwned> Json<T> {
+    fn encode(object: &T) -> Result<Vec<u8>, MDBXDeriveError> {
+        Ok(simd_json::to_vec(object)?)
+    }
+
+    fn decode(val: &[u8]) -> Result<T, MDBXDeriveError> {
+        // simd-json parses in place
+        let mut val = val.to_vec();
+        Ok(simd_json::from_slice(&mut val)?)
+    }
+}
+
+/// Raw reuses the big-endian key encoding, the same as `KeyAsTableObject`.
+impl<T: KeyObjectEncode + KeyObjectDecode> Raw<T> {
+    fn encode(object: &T) -> Result<Vec<u8>, MDBXDeriveError> {
+        object.key_encode()
+    }
+
+    fn decode(val: &[u8]) -> Result<T, MDBXDeriveError> {
+        T::key_decode(val)
+    }
+}
+
+impl<T: KeyObjectEncode + KeyObjectDecode> FixedSizeObject for Raw<T> {
+    const FIXED_SIZE: usize = T::KEYSIZE;
+}
+
+impl<C: ObjectCodec> ObjectCodec for Zstd<C> {
+    type Object = C::Object;
+
+    fn encode_object(object: &Self::Object) -> Result<Vec<u8>, MDBXDeriveError> {
+        let bs = C::encode_object(object)?;
+        Ok(zstd::encode_all(Cursor::new(bs), 1)?)
+    }
+
+    fn decode_object(val: &[u8]) -> Result<Self::Object, MDBXDeriveError> {
+        let decompressed = zstd::decode_all(val)?;
+        C::decode_object(&decompressed)
+    }
+
+    fn wrap(object: Self::Object) -> Self {
+        Self(C::wrap(object))
+    }
+
+    fn into_object(self) -> Self::Object {
+        self.0.into_object()
+    }
+
+    fn object(&self) -> &Self::Object {
+        self.0.object()
+    }
+}
+
+impl<C: TableObjectEncode> TableObjectEncode for Zstd<C> {
+    fn table_encode(&self) -> Result<Vec<u8>, MDBXDeriveError> {
+        let bs = self.0.table_encode()?;
+        Ok(zstd::encode_all(Cursor::new(bs), 1)?)
+    }
+
+    fn codec_id() -> Cow<'static, str> {
+        Cow::Owned(format!("Zstd<{}>", C::codec_id()))
+    }
+}
+
+impl<C: TableObjectDecode> TableObjectDecode for Zstd<C> {
+    fn table_decode(val: &[u8]) -> Result<Self, MDBXDeriveError> {
+        let decompressed = zstd::decode_all(val)?;
+        Ok(Self(C::table_decode(&decompressed)?))
+    }
+}
+
+pub trait LegacyCodecs<T> {
+    fn decode_legacy(val: &[u8]) -> Option<(T, usize)>;
+}
+
+impl<T> LegacyCodecs<T> for () {
+    fn decode_legacy(_val: &[u8]) -> Option<(T, usize)> {
+        None
+    }
+}
+
+impl<T, Head, Tail> LegacyCodecs<T> for (Head, Tail)
+where
+    Head: ObjectCodec<Object = T>,
+    Tail: LegacyCodecs<T>,
+{
+    fn decode_legacy(val: &[u8]) -> Option<(T, usize)> {
+        match Head::decode_object(val) {
+            Ok(object) => Some((object, 1)),
+            Err(_) => Tail::decode_legacy(val).map(|(object, format)| (object, format + 1)),
+        }
+    }
+}
+
+pub trait SniffedObject {
+    /// 0 is the primary format, legacy formats are numbered from 1 in declaration order
+    fn format(&self) -> usize;
+
+    fn is_legacy(&self) -> bool {
+        self.format() != 0
+    }
+}
+
+/// Encodes with `C` and decodes with `C` first, then each codec of the `tuple_list` `L`.
+pub struct Fallback<C: ObjectCodec, L> {
+    object: C::Object,
+    format: usize,
+    _legacy: PhantomData<fn() -> L>,
+}
+
+impl<C: ObjectCodec, L> Fallback<C, L> {
+    pub fn new(object: C::Object) -> Self {
+        Self {
+            object,
+            format: 0,
+            _legacy: PhantomData,
+        }
+    }
+}
+
+impl<C: ObjectCodec, L> SniffedObject for Fallback<C, L> {
+    fn format(&self) -> usize {
+        self.format
+    }
+}
+
+impl<C: ObjectCodec, L> Deref for Fallback<C, L> {
+    type Target = C::Object;
+    fn deref(&self) -> &Self::Target {
+        &self.object
+    }
+}
+
+impl<C: ObjectCodec, L> DerefMut for Fallback<C, L> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.object
+    }
+}
+
+impl<C, L> std::fmt::Debug for Fallback<C, L>
+where
+    C: ObjectCodec,
+    C::Object: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Fallback")
+            .field("object", &self.object)
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+impl<C, L> Clone for Fallback<C, L>
+where
+    C: ObjectCodec,
+    C::Object: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            object: self.object.clone(),
+            format: self.format,
+            _legacy: PhantomData,
+        }
+    }
+}
+
+impl<C: ObjectCodec, L: LegacyCodecs<C::Object>> ObjectCodec for Fallback<C, L> {
+    type Object = C::Object;
+
+    fn encode_object(object: &Self::Object) -> Result<Vec<u8>, MDBXDeriveError> {
+        C::encode_object(object)
+    }
+
+    fn decode_object(val: &[u8]) -> Result<Self::Object, MDBXDeriveError> {
+        Ok(Self::table_decode(val)?.object)
+    }
+
+    fn wrap(object: Self::Object) -> Self {
+        Self::new(object)
+    }
+
+    fn into_object(self) -> Self::Object {
+        self.object
+    }
+
+    fn object(&self) -> &Self::Object {
+        &self.object
+    }
+}
+
+impl<C: ObjectCodec + TableObjectEncode, L> TableObjectEncode for Fallback<C, L> {
+    fn table_encode(&self) -> Result<Vec<u8>, MDBXDeriveError> {
+        C::encode_object(&self.object)
+    }
+
+    /// Legacy codecs don't change what is written
+    fn codec_id() -> Cow<'static, str> {
+        C::codec_id()
+    }
+}
+
+impl<C: ObjectCodec, L: LegacyCodecs<C::Object>> TableObjectDecode for Fallback<C, L> {
+    fn table_decode(val: &[u8]) -> Result<Self, MDBXDeriveError> {
+        let (object, format) = match C::decode_object(val) {
+            Ok(object) => (object, 0),
+            Err(e) => L::decode_legacy(val).ok_or(e)?,
+        };
+        Ok(Self {
+            object,
+            format,
+            _legacy: PhantomData,
+        })
+    }
+}
+
+#[cfg(feature = "mdbx")]
+impl<C: ObjectCodec, L: LegacyCodecs<C::Object>> libmdbx_remote::TableObject for Fallback<C, L> {
+    fn decode(data_val: &[u8]) -> Result<Self, libmdbx_remote::Error> {
+        <Self as TableObjectDecode>::table_decode(data_val)
+            .map_err(|_| libmdbx_remote::Error::Corrupted)
+    }
+}
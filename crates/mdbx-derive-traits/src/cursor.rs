@@ -0,0 +1,292 @@
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+
+use futures::Stream;
+use libmdbx_remote::{CursorAny, TransactionKind};
+
+use crate::{
+    error::MDBXDeriveError,
+    key::{KeyObjectDecode, KeyObjectEncode},
+    orm::{MDBXDupFixedTable, MDBXDupSortTable, MDBXTable},
+    table::{FixedSizeObject, TableObjectDecode, TableObjectEncode},
+};
+
+pub type TableItem<T> = (<T as MDBXTable>::Key, <T as MDBXTable>::Value);
+pub type RawBounds = (Bound<Vec<u8>>, Bound<Vec<u8>>);
+pub(crate) type RawItem = (Vec<u8>, Vec<u8>);
+
+/// A cursor over a `MDBXTable` that decodes keys and values.
+pub struct TableCursor<T, K: TransactionKind> {
+    cursor: CursorAny<K>,
+    _table: PhantomData<fn() -> T>,
+}
+
+impl<T: MDBXTable, K: TransactionKind> TableCursor<T, K> {
+    pub fn new(cursor: CursorAny<K>) -> Self {
+        Self {
+            cursor,
+            _table: PhantomData,
+        }
+    }
+
+    pub fn cursor(&mut self) -> &mut CursorAny<K> {
+        &mut self.cursor
+    }
+
+    pub fn into_inner(self) -> CursorAny<K> {
+        self.cursor
+    }
+
+    fn decode(item: Option<RawItem>) -> Result<Option<TableItem<T>>, T::Error> {
+        let Some((k, v)) = item else {
+            return Ok(None);
+        };
+        Ok(Some((T::Key::key_decode(&k)?, T::Value::table_decode(&v)?)))
+    }
+
+    pub async fn first(&mut self) -> Result<Option<TableItem<T>>, T::Error> {
+        Self::decode(self.cursor.first().await?)
+    }
+
+    pub async fn last(&mut self) -> Result<Option<TableItem<T>>, T::Error> {
+        Self::decode(self.cursor.last().await?)
+    }
+
+    pub async fn next(&mut self) -> Result<Option<TableItem<T>>, T::Error> {
+        Self::decode(self.cursor.next().await?)
+    }
+
+    pub async fn prev(&mut self) -> Result<Option<TableItem<T>>, T::Error> {
+        Self::decode(self.cursor.prev().await?)
+    }
+
+    pub async fn current(&mut self) -> Result<Option<TableItem<T>>, T::Error> {
+        Self::decode(self.cursor.get_current().await?)
+    }
+
+    /// Positions at the first item whose key is greater than or equal to `key`.
+    pub async fn seek(&mut self, key: &T::Key) -> Result<Option<TableItem<T>>, T::Error> {
+        let key = key.key_encode()?;
+        Self::decode(self.cursor.set_range(&key).await?)
+    }
+
+    /// Positions at `key` only if it exists.
+    pub async fn seek_exact(&mut self, key: &T::Key) -> Result<Option<TableItem<T>>, T::Error> {
+        let key = key.key_encode()?;
+        Self::decode(self.cursor.set_key(&key).await?)
+    }
+
+    /// Positions at the first item of `range` in its scan direction.
+    async fn seek_range(&mut self, range: &RawRange) -> Result<Option<RawItem>, T::Error> {
+        let cursor = &mut self.cursor;
+        if !range.reverse {
+            return Ok(match &range.lower {
+                Bound::Unbounded => cursor.first().await?,
+                Bound::Included(lower) => cursor.set_range(lower).await?,
+                Bound::Excluded(lower) => {
+                    match cursor.set_range::<Vec<u8>, Vec<u8>>(lower).await? {
+                        Some((k, _)) if &k == lower => cursor.next_nodup().await?,
+                        item => item,
+                    }
+                }
+            });
+        }
+
+        // Seek past the upper bound, then step back
+        let past = match &range.upper {
+            Bound::Unbounded => None,
+            Bound::Included(upper) => match cursor.set_range::<Vec<u8>, Vec<u8>>(upper).await? {
+                Some((k, _)) if &k == upper => cursor.next_nodup().await?,
+                item => item,
+            },
+            Bound::Excluded(upper) => cursor.set_range(upper).await?,
+        };
+        Ok(match past {
+            Some(_) => cursor.prev().await?,
+            None => cursor.last().await?,
+        })
+    }
+
+    /// Streams the items whose raw key is within `lower` and `upper`, in descending key order
+    /// if `reverse` is set. The scan stops at the bound without decoding the item past it.
+    pub fn into_range_stream(
+        self,
+        lower: Bound<Vec<u8>>,
+        upper: Bound<Vec<u8>>,
+        reverse: bool,
+    ) -> impl Stream<Item = Result<TableItem<T>, T::Error>> + Send {
+        let range = RawRange {
+            lower,
+            upper,
+            reverse,
+        };
+        futures::stream::try_unfold((self, range, true), |(mut cur, range, start)| async move {
+            let item = if start {
+                cur.seek_range(&range).await?
+            } else if range.reverse {
+                cur.cursor.prev().await?
+            } else {
+                cur.cursor.next().await?
+            };
+            match item {
+                Some((k, v)) if range.within_end(&k) => {
+                    let item = (T::Key::key_decode(&k)?, T::Value::table_decode(&v)?);
+                    Ok(Some((item, (cur, range, false))))
+                }
+                _ => Ok(None),
+            }
+        })
+    }
+
+    /// Streams all items from the start of the table.
+    pub fn into_stream(self) -> impl Stream<Item = Result<TableItem<T>, T::Error>> + Send {
+        self.into_range_stream(Bound::Unbounded, Bound::Unbounded, false)
+    }
+
+    /// Streams all items starting from the first key greater than or equal to `key`.
+    pub fn into_stream_from(
+        self,
+        key: &T::Key,
+    ) -> Result<impl Stream<Item = Result<TableItem<T>, T::Error>> + Send, T::Error> {
+        let key = key.key_encode()?;
+        Ok(self.into_range_stream(Bound::Included(key), Bound::Unbounded, false))
+    }
+}
+
+impl<T: MDBXDupSortTable, K: TransactionKind> TableCursor<T, K> {
+    fn decode_value(value: Option<Vec<u8>>) -> Result<Option<T::Value>, T::Error> {
+        Ok(value.map(|v| T::Value::table_decode(&v)).transpose()?)
+    }
+
+    pub async fn first_dup(&mut self) -> Result<Option<T::Value>, T::Error> {
+        Self::decode_value(self.cursor.first_dup().await?)
+    }
+
+    pub async fn last_dup(&mut self) -> Result<Option<T::Value>, T::Error> {
+        Self::decode_value(self.cursor.last_dup().await?)
+    }
+
+    pub async fn next_dup(&mut self) -> Result<Option<TableItem<T>>, T::Error> {
+        Self::decode(self.cursor.next_dup().await?)
+    }
+
+    pub async fn prev_dup(&mut self) -> Result<Option<TableItem<T>>, T::Error> {
+        Self::decode(self.cursor.prev_dup().await?)
+    }
+
+    pub async fn next_nodup(&mut self) -> Result<Option<TableItem<T>>, T::Error> {
+        Self::decode(self.cursor.next_nodup().await?)
+    }
+
+    pub async fn prev_nodup(&mut self) -> Result<Option<TableItem<T>>, T::Error> {
+        Self::decode(self.cursor.prev_nodup().await?)
+    }
+
+    pub async fn get_both(
+        &mut self,
+        key: &T::Key,
+        value: &T::Value,
+    ) -> Result<Option<T::Value>, T::Error> {
+        let (key, value) = (key.key_encode()?, value.table_encode()?);
+        Self::decode_value(self.cursor.get_both(&key, &value).await?)
+    }
+
+    pub async fn get_both_range(
+        &mut self,
+        key: &T::Key,
+        value: &T::Value,
+    ) -> Result<Option<T::Value>, T::Error> {
+        let (key, value) = (key.key_encode()?, value.table_encode()?);
+        Self::decode_value(self.cursor.get_both_range(&key, &value).await?)
+    }
+}
+
+impl<T: MDBXDupFixedTable, K: TransactionKind> TableCursor<T, K> {
+    fn decode_page(page: Vec<u8>) -> Result<Vec<T::Value>, T::Error> {
+        if !page.len().is_multiple_of(T::Value::FIXED_SIZE) {
+            return Err(MDBXDeriveError::IncorrectSchema(page).into());
+        }
+        Ok(page
+            .chunks_exact(T::Value::FIXED_SIZE)
+            .map(T::Value::table_decode)
+            .collect::<Result<_, _>>()?)
+    }
+
+    /// Returns up to a page of duplicates from the current position, the cursor must be
+    /// positioned on a key already.
+    pub async fn get_multiple(&mut self) -> Result<Option<Vec<T::Value>>, T::Error> {
+        self.cursor
+            .get_multiple::<Vec<u8>>()
+            .await?
+            .map(Self::decode_page)
+            .transpose()
+    }
+
+    pub(crate) async fn next_multiple_raw(
+        &mut self,
+    ) -> Result<Option<(Vec<u8>, Vec<T::Value>)>, T::Error> {
+        let Some((k, page)) = self.cursor.next_multiple::<Vec<u8>, Vec<u8>>().await? else {
+            return Ok(None);
+        };
+        Ok(Some((k, Self::decode_page(page)?)))
+    }
+
+    /// Returns the next page of duplicates.
+    pub async fn next_multiple(&mut self) -> Result<Option<(T::Key, Vec<T::Value>)>, T::Error> {
+        let Some((k, page)) = self.next_multiple_raw().await? else {
+            return Ok(None);
+        };
+        Ok(Some((T::Key::key_decode(&k)?, page)))
+    }
+}
+
+struct RawRange {
+    lower: Bound<Vec<u8>>,
+    upper: Bound<Vec<u8>>,
+    reverse: bool,
+}
+
+impl RawRange {
+    /// Only the bound in the scan direction needs checking, the other one is where the scan starts.
+    fn within_end(&self, key: &[u8]) -> bool {
+        if self.reverse {
+            match &self.lower {
+                Bound::Unbounded => true,
+                Bound::Included(lower) => key >= lower.as_slice(),
+                Bound::Excluded(lower) => key > lower.as_slice(),
+            }
+        } else {
+            match &self.upper {
+                Bound::Unbounded => true,
+                Bound::Included(upper) => key <= upper.as_slice(),
+                Bound::Excluded(upper) => key < upper.as_slice(),
+            }
+        }
+    }
+}
+
+/// Encodes the bounds of a range of keys.
+pub fn encode_range<K: KeyObjectEncode>(
+    bounds: &impl RangeBounds<K>,
+) -> Result<RawBounds, MDBXDeriveError> {
+    let encode = |bound: Bound<&K>| -> Result<_, MDBXDeriveError> {
+        Ok(match bound {
+            Bound::Unbounded => Bound::Unbounded,
+            Bound::Included(key) => Bound::Included(key.key_encode()?),
+            Bound::Excluded(key) => Bound::Excluded(key.key_encode()?),
+        })
+    };
+    Ok((encode(bounds.start_bound())?, encode(bounds.end_bound())?))
+}
+
+/// The exclusive upper bound of all keys starting with `prefix`.
+pub fn prefix_end(prefix: &[u8]) -> Bound<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last != u8::MAX {
+            end.push(last + 1);
+            return Bound::Excluded(end);
+        }
+    }
+    Bound::Unbounded
+}
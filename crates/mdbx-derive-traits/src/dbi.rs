@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+
+use libmdbx_remote::{
+    ClientError, Environment, EnvironmentAny, EnvironmentBuilder, EnvironmentFlags, Info, Mode, RO,
+    RW, Stat, TransactionAny, TransactionKind, ffi,
+};
+
+use crate::orm::{HasMDBXEnvironment, MDBXTable};
+
+type DbiKey = (Option<&'static str>, u32);
+
+/// DBI handles of an environment keyed by table name and flags. Only handles of committed
+/// tables should be inserted, a handle created in an aborted transaction is invalid.
+#[derive(Debug, Clone, Default)]
+pub struct DbiCache {
+    dbis: Arc<RwLock<HashMap<DbiKey, u32>>>,
+}
+
+impl DbiCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cache shared by every handle of `env`, so that a table dropped through one of them
+    /// is forgotten by all. Remote environments can't be told apart and get a new cache.
+    pub fn for_env(env: &EnvironmentAny) -> Self {
+        env.as_local()
+            .map_or_else(Self::new, |env| local_dbi_cache(env).clone())
+    }
+
+    pub fn get<T: MDBXTable>(&self) -> Option<u32> {
+        self.dbis
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&(T::NAME, T::FLAGS.bits()))
+            .copied()
+    }
+
+    pub fn insert<T: MDBXTable>(&self, dbi: u32) {
+        self.dbis
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert((T::NAME, T::FLAGS.bits()), dbi);
+    }
+
+    pub fn remove<T: MDBXTable>(&self) -> Option<u32> {
+        self.dbis
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&(T::NAME, T::FLAGS.bits()))
+    }
+
+    pub fn clear(&self) {
+        self.dbis.write().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+/// The `DbiCache` of a local environment, kept in its user context. libmdbx has no hook to free
+/// it when the environment closes, so it lives as long as the process.
+pub(crate) fn local_dbi_cache(env: &Environment) -> &'static DbiCache {
+    static INIT: Mutex<()> = Mutex::new(());
+    let _init = INIT.lock().unwrap_or_else(|e| e.into_inner());
+    // SAFETY: the pointer is only used within the closure, while `env` is alive, and the user
+    // context is only ever set here to a leaked `DbiCache`
+    env.with_raw_env_ptr(|env| unsafe {
+        if let Some(cache) = (ffi::mdbx_env_get_userctx(env) as *const DbiCache).as_ref() {
+            return cache;
+        }
+        let cache: &'static DbiCache = Box::leak(Box::default());
+        ffi::mdbx_env_set_userctx(env, cache as *const DbiCache as *mut _);
+        cache
+    })
+}
+
+/// An environment with a `DbiCache`, for using the single-shot `MDBXTable` helpers without a
+/// database struct. A bare local `EnvironmentAny` caches as well, this is needed for remote
+/// ones.
+#[derive(Debug, Clone)]
+pub struct CachedEnvironment {
+    pub env: EnvironmentAny,
+    pub dbi_cache: DbiCache,
+}
+
+impl CachedEnvironment {
+    pub fn new(env: EnvironmentAny) -> Self {
+        Self {
+            dbi_cache: DbiCache::for_env(&env),
+            env,
+        }
+    }
+}
+
+impl Deref for CachedEnvironment {
+    type Target = EnvironmentAny;
+    fn deref(&self) -> &Self::Target {
+        &self.env
+    }
+}
+
+impl HasMDBXEnvironment for CachedEnvironment {
+    type Mode = RW;
+
+    fn env(&self) -> &EnvironmentAny {
+        &self.env
+    }
+
+    fn dbi_cache(&self) -> Option<&DbiCache> {
+        Some(&self.dbi_cache)
+    }
+}
+
+/// Opens the environment at `url` read-only, whatever the mode of `defaults` or the query of
+/// the URL. The flags of a builder can't be read back, so for local paths only `no_sub_dir` is
+/// kept, detected from the path.
+pub async fn open_env_read_only(
+    url: &str,
+    mut defaults: EnvironmentBuilder,
+) -> Result<EnvironmentAny, ClientError> {
+    if url.starts_with("mdbx") || url.starts_with("file") {
+        // `ro` takes precedence over `rw`
+        let sep = if url.contains('?') { '&' } else { '?' };
+        EnvironmentAny::open_with_defaults(&format!("{url}{sep}ro"), defaults).await
+    } else {
+        defaults.set_flags(EnvironmentFlags {
+            mode: Mode::ReadOnly,
+            no_sub_dir: Path::new(url).is_file(),
+            ..Default::default()
+        });
+        EnvironmentAny::open_with_defaults(url, defaults).await
+    }
+}
+
+/// The type of the `env` field of a database opened in this mode: the environment itself for
+/// `RW`, a [`ReadOnlyEnvironment`] for `RO`.
+pub trait DatabaseEnv: TransactionKind {
+    type Env: Clone + std::fmt::Debug + Send + Sync;
+
+    fn wrap_env(env: EnvironmentAny) -> Self::Env;
+
+    fn env_any(env: &Self::Env) -> &EnvironmentAny;
+}
+
+impl DatabaseEnv for RW {
+    type Env = EnvironmentAny;
+
+    fn wrap_env(env: EnvironmentAny) -> Self::Env {
+        env
+    }
+
+    fn env_any(env: &Self::Env) -> &EnvironmentAny {
+        env
+    }
+}
+
+impl DatabaseEnv for RO {
+    type Env = ReadOnlyEnvironment;
+
+    fn wrap_env(env: EnvironmentAny) -> Self::Env {
+        ReadOnlyEnvironment(env)
+    }
+
+    fn env_any(env: &Self::Env) -> &EnvironmentAny {
+        &env.0
+    }
+}
+
+/// The environment of a read-only database, which only begins read transactions.
+#[derive(Debug, Clone)]
+pub struct ReadOnlyEnvironment(EnvironmentAny);
+
+impl ReadOnlyEnvironment {
+    pub async fn begin_ro_txn(&self) -> Result<TransactionAny<RO>, ClientError> {
+        self.0.begin_ro_txn().await
+    }
+
+    pub async fn stat(&self) -> Result<Stat, ClientError> {
+        self.0.stat().await
+    }
+
+    pub async fn info(&self) -> Result<Info, ClientError> {
+        self.0.info().await
+    }
+}
+
+/// Doubles the upper size of the map of a local environment, so that a write that failed
+/// with `MapFull` can run again. Returns `false` for remote environments, whose map can't be
+/// grown by a client, and when the mapping can't be extended in place: environments are
+/// opened with `MDBX_NOSTICKYTHREADS`, so libmdbx never moves an existing mapping. There must
+/// be no write transaction open in this process.
+pub fn grow_map(env: &EnvironmentAny) -> Result<bool, ClientError> {
+    let Some(env) = env.as_local() else {
+        return Ok(false);
+    };
+    // SAFETY: the pointer is only used within the closure, while `env` is alive
+    let rc = env.with_raw_env_ptr(|env| unsafe {
+        let mut info = std::mem::zeroed::<ffi::MDBX_envinfo>();
+        let rc = ffi::mdbx_env_info_ex(
+            env,
+            std::ptr::null(),
+            &mut info,
+            size_of::<ffi::MDBX_envinfo>(),
+        );
+        if rc != 0 {
+            return rc;
+        }
+        let upper = isize::try_from(info.mi_geo.upper.saturating_mul(2)).unwrap_or(isize::MAX);
+        ffi::mdbx_env_set_geometry(env, -1, -1, upper, -1, -1, -1)
+    });
+    match rc {
+        0 => Ok(true),
+        ffi::MDBX_UNABLE_EXTEND_MAPSIZE => Ok(false),
+        rc => Err(ClientError::MDBX(libmdbx_remote::Error::from_err_code(rc))),
+    }
+}
@@ -24,6 +24,34 @@ pub enum MDBXDeriveError {
     #[cfg(feature = "mdbx")]
     #[error("mdbx-remote: {0}")]
     Client(libmdbx_remote::ClientError),
+    #[cfg(feature = "mdbx")]
+    #[error("commit: {0}")]
+    Commit(libmdbx_remote::ClientError),
+    #[cfg(feature = "mdbx")]
+    #[error("transaction aborted on commit")]
+    CommitAborted,
+    #[cfg(feature = "mdbx")]
+    #[error("{0} is not supported by remote environments")]
+    RemoteUnsupported(&'static str),
+    #[cfg(feature = "mdbx")]
+    #[error("migration version {0} is zero or registered twice")]
+    MigrationVersion(u64),
+    #[cfg(feature = "mdbx")]
+    #[error("schema fingerprint mismatch in tables {0:?}")]
+    SchemaMismatch(Vec<String>),
+    #[cfg(feature = "mdbx")]
+    #[error("table {table:?} has flags {actual:#x}, expected {expected:#x}")]
+    FlagsMismatch {
+        table: String,
+        expected: u32,
+        actual: u32,
+    },
+    #[cfg(feature = "mdbx")]
+    #[error("table {0:?} does not exist")]
+    MissingTable(&'static str),
+    #[cfg(feature = "mdbx")]
+    #[error("table {0:?} is part of the database twice")]
+    DuplicateTable(&'static str),
     #[error("bcs: {0}")]
     BCS(#[from] bcs::Error),
 }
@@ -37,3 +65,26 @@ impl From<libmdbx_remote::ClientError> for MDBXDeriveError {
         }
     }
 }
+
+impl MDBXDeriveError {
+    /// Errors that may go away by running the transaction again as is.
+    #[cfg(feature = "mdbx")]
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::MDBX(libmdbx_remote::Error::Busy))
+    }
+
+    /// The map reached its upper size, which `grow_map` can raise for local environments.
+    #[cfg(feature = "mdbx")]
+    pub fn is_map_full(&self) -> bool {
+        matches!(
+            self,
+            Self::MDBX(libmdbx_remote::Error::MapFull)
+                | Self::Client(libmdbx_remote::ClientError::MDBX(
+                    libmdbx_remote::Error::MapFull
+                ))
+                | Self::Commit(libmdbx_remote::ClientError::MDBX(
+                    libmdbx_remote::Error::MapFull
+                ))
+        )
+    }
+}
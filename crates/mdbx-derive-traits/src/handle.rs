@@ -0,0 +1,311 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::RangeBounds;
+
+use futures::Stream;
+use libmdbx_remote::{RW, TransactionAny, TransactionKind, WriteFlags};
+
+use crate::{
+    cursor::{TableCursor, TableItem},
+    key::KeyObjectEncode,
+    orm::MDBXTable,
+    stat::TableStat,
+    table::TableObjectEncode,
+};
+
+/// A `MDBXTable` with its DBI bound, as returned by the accessors of the generated DBI structs.
+/// Errors are the table's own `MDBXTable::Error`. The write methods need the `RW` mode.
+pub struct TableHandle<T, M = RW> {
+    dbi: u32,
+    _table: PhantomData<fn() -> (T, M)>,
+}
+
+impl<T, M> Clone for TableHandle<T, M> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, M> Copy for TableHandle<T, M> {}
+
+impl<T, M> fmt::Debug for TableHandle<T, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TableHandle")
+            .field("table", &std::any::type_name::<T>())
+            .field("mode", &std::any::type_name::<M>())
+            .field("dbi", &self.dbi)
+            .finish()
+    }
+}
+
+impl<T: MDBXTable, M: TransactionKind> TableHandle<T, M> {
+    pub const fn new(dbi: u32) -> Self {
+        Self {
+            dbi,
+            _table: PhantomData,
+        }
+    }
+
+    pub const fn dbi(&self) -> u32 {
+        self.dbi
+    }
+
+    pub fn get<K: TransactionKind>(
+        &self,
+        tx: &TransactionAny<K>,
+        key: &T::Key,
+    ) -> impl Future<Output = Result<Option<T::Value>, T::Error>> + Send {
+        T::get_item_tx(tx, Some(self.dbi), key)
+    }
+
+    pub fn get_many<K: TransactionKind>(
+        &self,
+        tx: &TransactionAny<K>,
+        keys: impl IntoIterator<Item = impl Borrow<T::Key>>,
+    ) -> impl Future<Output = Result<Vec<Option<T::Value>>, T::Error>> + Send {
+        T::get_many_tx(tx, Some(self.dbi), keys)
+    }
+
+    pub fn cursor<K: TransactionKind>(
+        &self,
+        tx: &TransactionAny<K>,
+    ) -> impl Future<Output = Result<TableCursor<T, K>, T::Error>> + Send {
+        T::cursor_tx(tx, Some(self.dbi))
+    }
+
+    pub fn range<K: TransactionKind>(
+        &self,
+        tx: &TransactionAny<K>,
+        bounds: impl RangeBounds<T::Key>,
+    ) -> impl Future<
+        Output = Result<impl Stream<Item = Result<TableItem<T>, T::Error>> + Send, T::Error>,
+    > + Send {
+        T::range_tx(tx, Some(self.dbi), bounds)
+    }
+
+    pub fn range_rev<K: TransactionKind>(
+        &self,
+        tx: &TransactionAny<K>,
+        bounds: impl RangeBounds<T::Key>,
+    ) -> impl Future<
+        Output = Result<impl Stream<Item = Result<TableItem<T>, T::Error>> + Send, T::Error>,
+    > + Send {
+        T::range_rev_tx(tx, Some(self.dbi), bounds)
+    }
+
+    pub fn prefix<K: TransactionKind>(
+        &self,
+        tx: &TransactionAny<K>,
+        prefix: &[u8],
+    ) -> impl Future<
+        Output = Result<impl Stream<Item = Result<TableItem<T>, T::Error>> + Send, T::Error>,
+    > + Send {
+        T::prefix_tx(tx, Some(self.dbi), prefix)
+    }
+
+    pub fn prefix_rev<K: TransactionKind>(
+        &self,
+        tx: &TransactionAny<K>,
+        prefix: &[u8],
+    ) -> impl Future<
+        Output = Result<impl Stream<Item = Result<TableItem<T>, T::Error>> + Send, T::Error>,
+    > + Send {
+        T::prefix_rev_tx(tx, Some(self.dbi), prefix)
+    }
+
+    pub fn stat<K: TransactionKind>(
+        &self,
+        tx: &TransactionAny<K>,
+    ) -> impl Future<Output = Result<TableStat, T::Error>> + Send {
+        T::stat_tx(tx, Some(self.dbi))
+    }
+}
+
+impl<T: MDBXTable> TableHandle<T, RW> {
+    pub fn put(
+        &self,
+        tx: &TransactionAny<RW>,
+        key: &T::Key,
+        value: &T::Value,
+        flags: WriteFlags,
+    ) -> impl Future<Output = Result<(), T::Error>> + Send {
+        T::put_item_tx(tx, Some(self.dbi), key, value, flags)
+    }
+
+    pub fn put_many(
+        &self,
+        tx: &TransactionAny<RW>,
+        items: impl IntoIterator<Item = (impl Borrow<T::Key>, impl Borrow<T::Value>)>,
+        flags: WriteFlags,
+    ) -> impl Future<Output = Result<usize, T::Error>> + Send {
+        T::put_many_tx(tx, Some(self.dbi), items, flags)
+    }
+
+    /// Deletes the key, or only its duplicate `value` in a DUPSORT table. Returns whether
+    /// anything was deleted.
+    pub async fn del(
+        &self,
+        tx: &TransactionAny<RW>,
+        key: &T::Key,
+        value: Option<&T::Value>,
+    ) -> Result<bool, T::Error> {
+        let value = value.map(|v| v.table_encode()).transpose()?;
+        Ok(tx
+            .del(self.dbi, &key.key_encode()?, value.as_deref())
+            .await?)
+    }
+
+    pub fn delete_many(
+        &self,
+        tx: &TransactionAny<RW>,
+        keys: impl IntoIterator<Item = impl Borrow<T::Key>>,
+    ) -> impl Future<Output = Result<usize, T::Error>> + Send {
+        T::delete_many_tx(tx, Some(self.dbi), keys)
+    }
+
+    pub fn clear(
+        &self,
+        tx: &TransactionAny<RW>,
+    ) -> impl Future<Output = Result<(), T::Error>> + Send {
+        T::clear_tx(tx, Some(self.dbi))
+    }
+}
+
+/// A `TableHandle` together with the transaction it is used in, as returned by the table
+/// accessors of `DbTxn`. Writing needs a `RW` transaction and a `RW` handle, `read_only`
+/// tables have `RO` handles in any transaction.
+pub struct TxnTable<'t, T, K: TransactionKind, M = K> {
+    tx: &'t TransactionAny<K>,
+    handle: TableHandle<T, M>,
+}
+
+impl<T, K: TransactionKind, M> Clone for TxnTable<'_, T, K, M> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, K: TransactionKind, M> Copy for TxnTable<'_, T, K, M> {}
+
+impl<T, K: TransactionKind, M> fmt::Debug for TxnTable<'_, T, K, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TxnTable")
+            .field("table", &std::any::type_name::<T>())
+            .field("tx", self.tx)
+            .field("dbi", &self.handle.dbi)
+            .finish()
+    }
+}
+
+impl<'t, T: MDBXTable, K: TransactionKind, M: TransactionKind> TxnTable<'t, T, K, M> {
+    /// Only `DbTxn` pairs handles with transactions, so that both belong to the same database.
+    pub(crate) const fn new(tx: &'t TransactionAny<K>, handle: TableHandle<T, M>) -> Self {
+        Self { tx, handle }
+    }
+
+    pub const fn dbi(&self) -> u32 {
+        self.handle.dbi
+    }
+
+    pub const fn tx(&self) -> &'t TransactionAny<K> {
+        self.tx
+    }
+
+    pub fn get(
+        &self,
+        key: &T::Key,
+    ) -> impl Future<Output = Result<Option<T::Value>, T::Error>> + Send {
+        self.handle.get(self.tx, key)
+    }
+
+    pub fn get_many(
+        &self,
+        keys: impl IntoIterator<Item = impl Borrow<T::Key>>,
+    ) -> impl Future<Output = Result<Vec<Option<T::Value>>, T::Error>> + Send {
+        self.handle.get_many(self.tx, keys)
+    }
+
+    pub fn cursor(&self) -> impl Future<Output = Result<TableCursor<T, K>, T::Error>> + Send {
+        self.handle.cursor(self.tx)
+    }
+
+    pub fn range(
+        &self,
+        bounds: impl RangeBounds<T::Key>,
+    ) -> impl Future<
+        Output = Result<impl Stream<Item = Result<TableItem<T>, T::Error>> + Send, T::Error>,
+    > + Send {
+        self.handle.range(self.tx, bounds)
+    }
+
+    pub fn range_rev(
+        &self,
+        bounds: impl RangeBounds<T::Key>,
+    ) -> impl Future<
+        Output = Result<impl Stream<Item = Result<TableItem<T>, T::Error>> + Send, T::Error>,
+    > + Send {
+        self.handle.range_rev(self.tx, bounds)
+    }
+
+    pub fn prefix(
+        &self,
+        prefix: &[u8],
+    ) -> impl Future<
+        Output = Result<impl Stream<Item = Result<TableItem<T>, T::Error>> + Send, T::Error>,
+    > + Send {
+        self.handle.prefix(self.tx, prefix)
+    }
+
+    pub fn prefix_rev(
+        &self,
+        prefix: &[u8],
+    ) -> impl Future<
+        Output = Result<impl Stream<Item = Result<TableItem<T>, T::Error>> + Send, T::Error>,
+    > + Send {
+        self.handle.prefix_rev(self.tx, prefix)
+    }
+
+    pub fn stat(&self) -> impl Future<Output = Result<TableStat, T::Error>> + Send {
+        self.handle.stat(self.tx)
+    }
+}
+
+impl<T: MDBXTable> TxnTable<'_, T, RW, RW> {
+    pub fn put(
+        &self,
+        key: &T::Key,
+        value: &T::Value,
+        flags: WriteFlags,
+    ) -> impl Future<Output = Result<(), T::Error>> + Send {
+        self.handle.put(self.tx, key, value, flags)
+    }
+
+    pub fn put_many(
+        &self,
+        items: impl IntoIterator<Item = (impl Borrow<T::Key>, impl Borrow<T::Value>)>,
+        flags: WriteFlags,
+    ) -> impl Future<Output = Result<usize, T::Error>> + Send {
+        self.handle.put_many(self.tx, items, flags)
+    }
+
+    /// Deletes the key, or only its duplicate `value` in a DUPSORT table.
+    pub fn del(
+        &self,
+        key: &T::Key,
+        value: Option<&T::Value>,
+    ) -> impl Future<Output = Result<bool, T::Error>> + Send {
+        self.handle.del(self.tx, key, value)
+    }
+
+    pub fn delete_many(
+        &self,
+        keys: impl IntoIterator<Item = impl Borrow<T::Key>>,
+    ) -> impl Future<Output = Result<usize, T::Error>> + Send {
+        self.handle.delete_many(self.tx, keys)
+    }
+
+    pub fn clear(&self) -> impl Future<Output = Result<(), T::Error>> + Send {
+        self.handle.clear(self.tx)
+    }
+}
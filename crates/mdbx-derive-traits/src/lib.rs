@@ -1,10 +1,27 @@
 #![allow(non_snake_case)]
 
+#[cfg(feature = "mdbx")]
+pub mod batch;
+pub mod codec;
+#[cfg(feature = "mdbx")]
+pub mod cursor;
+#[cfg(feature = "mdbx")]
+pub mod dbi;
 pub mod error;
+#[cfg(feature = "mdbx")]
+pub mod handle;
 pub mod key;
 #[cfg(feature = "mdbx")]
+pub mod migration;
+#[cfg(feature = "mdbx")]
 pub mod orm;
+#[cfg(feature = "mdbx")]
+pub mod schema;
+#[cfg(feature = "mdbx")]
+pub mod stat;
 pub mod table;
+#[cfg(feature = "mdbx")]
+pub mod txn;
 
 #[cfg(feature = "alloy")]
 pub mod alloy;
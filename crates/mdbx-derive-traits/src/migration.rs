@@ -0,0 +1,97 @@
+use futures::future::BoxFuture;
+use libmdbx_remote::{
+    ClientError, DatabaseFlags, EnvironmentAny, RW, TransactionAny, TransactionKind, WriteFlags,
+};
+
+use crate::{
+    error::MDBXDeriveError,
+    orm::{DATABASE_METADATA_NAME, open_reserved_table_tx},
+    txn::WriteTxn,
+};
+
+/// The key of the applied schema version in the database metadata table.
+pub const SCHEMA_VERSION_KEY: &[u8] = b"schema_version";
+
+pub type MigrationFn<D, E> =
+    for<'t> fn(&'t TransactionAny<RW>, &'t D) -> BoxFuture<'t, Result<(), E>>;
+
+/// A schema migration of a database with the DBI struct `D`, applied once the schema version
+/// of the database is below `version`.
+pub struct Migration<D, E> {
+    pub version: u64,
+    pub up: MigrationFn<D, E>,
+}
+
+impl<D, E> Migration<D, E> {
+    pub const fn new(version: u64, up: MigrationFn<D, E>) -> Self {
+        Self { version, up }
+    }
+}
+
+/// A database without any applied migration is at version 0.
+pub async fn schema_version_tx<K: TransactionKind>(
+    tx: &TransactionAny<K>,
+) -> Result<u64, MDBXDeriveError> {
+    let Some(dbi) = open_reserved_table_tx(tx, DATABASE_METADATA_NAME).await? else {
+        return Ok(0);
+    };
+    let Some(v) = tx.get::<Vec<u8>>(dbi, SCHEMA_VERSION_KEY).await? else {
+        return Ok(0);
+    };
+    let v = <[u8; 8]>::try_from(v.as_slice()).map_err(|_| MDBXDeriveError::IncorrectSchema(v))?;
+    Ok(u64::from_be_bytes(v))
+}
+
+pub async fn write_schema_version_tx(
+    tx: &TransactionAny<RW>,
+    version: u64,
+) -> Result<(), MDBXDeriveError> {
+    let dbi = tx
+        .create_db(Some(DATABASE_METADATA_NAME), DatabaseFlags::empty())
+        .await?
+        .dbi();
+    tx.put(
+        dbi,
+        SCHEMA_VERSION_KEY,
+        &version.to_be_bytes(),
+        WriteFlags::default(),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Applies the migrations above the schema version of the database in version order, then
+/// records the last version. All of them run in a single transaction, so either every
+/// pending migration is applied or none is. Returns the schema version afterwards.
+pub async fn run_migrations<D, E>(
+    env: &EnvironmentAny,
+    dbis: &D,
+    migrations: &[Migration<D, E>],
+) -> Result<u64, E>
+where
+    E: From<ClientError> + From<MDBXDeriveError>,
+{
+    let mut pending: Vec<_> = migrations.iter().collect();
+    pending.sort_by_key(|m| m.version);
+    for (i, m) in pending.iter().enumerate() {
+        if m.version == 0 || pending.get(i + 1).is_some_and(|n| n.version == m.version) {
+            return Err(MDBXDeriveError::MigrationVersion(m.version).into());
+        }
+    }
+
+    let tx = WriteTxn::begin(env).await?;
+    let result: Result<_, E> = async {
+        let current = schema_version_tx(&tx).await?;
+        let mut version = current;
+        for m in pending.into_iter().filter(|m| m.version > current) {
+            (m.up)(&tx, dbis).await?;
+            version = m.version;
+        }
+        if version != current {
+            write_schema_version_tx(&tx, version).await?;
+        }
+        Ok(version)
+    }
+    .await;
+    tx.finish(result).await
+}
@@ -1,29 +1,110 @@
+use std::borrow::Borrow;
 use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use std::time::Duration;
 
-use libmdbx_remote::{DatabaseFlags, EnvironmentAny, RW, TransactionKind, WriteFlags};
+use futures::{Stream, future::BoxFuture};
+
+use libmdbx_remote::{
+    ClientError, CursorAny, DatabaseFlags, EnvironmentAny, RO, RW, TransactionAny, TransactionKind,
+    WriteFlags,
+};
 
 use crate::{
+    batch::{delete_many_raw, encode_items, encode_keys, get_many_raw, put_many_raw},
+    codec::SniffedObject,
+    cursor::{TableCursor, TableItem, encode_range, prefix_end},
+    dbi::{DbiCache, grow_map, local_dbi_cache},
     error::MDBXDeriveError,
+    handle::TableHandle,
     key::{KeyObjectDecode, KeyObjectEncode},
-    table::{TableObjectDecode, TableObjectEncode},
+    migration::schema_version_tx,
+    schema::{
+        Fingerprints, check_flags_tx, check_table_flags_tx, combined_fingerprint, is_table_tx,
+        mismatched_tables, stored_fingerprints_tx, table_exists_tx, table_fingerprint,
+        write_fingerprints_tx,
+    },
+    stat::TableStat,
+    table::{FixedSizeObject, TableObjectDecode, TableObjectEncode},
+    txn::{DbTxn, WriteTxn},
 };
 
 pub fn type_eq<T: ?Sized, U: ?Sized>() -> bool {
     typeid::of::<T>() == typeid::of::<U>()
 }
 
+/// The reserved table holding the metadata of every table, keyed by table name.
+pub const TABLE_METADATA_NAME: &str = "__table_metadata";
+/// The reserved table holding the metadata of a `MDBXDatabase`.
+pub const DATABASE_METADATA_NAME: &str = "__database_metadata";
+
+/// Opens a reserved table, or returns `None` if nothing created it yet. The table is looked
+/// up first, as a failed open leaves libmdbx in a state that trips its assertions once a
+/// write transaction drops a table or aborts.
+pub(crate) async fn open_reserved_table_tx<K: TransactionKind>(
+    tx: &libmdbx_remote::TransactionAny<K>,
+    name: &str,
+) -> Result<Option<u32>, MDBXDeriveError> {
+    if !table_exists_tx(tx, Some(name)).await? {
+        return Ok(None);
+    }
+    Ok(Some(tx.open_db(Some(name)).await?.dbi()))
+}
+
+/// Reads the metadata of `D` from the main DB, where older versions kept it. The main DB also
+/// holds the records of named tables, which are skipped. Remote environments can't list their
+/// tables, there only the tables of `D` are known.
+async fn legacy_metadata_tx<D: MDBXDatabase, K: TransactionKind>(
+    tx: &libmdbx_remote::TransactionAny<K>,
+) -> Result<Option<(u32, Vec<u8>)>, MDBXDeriveError> {
+    let key = D::METADATA_NAME;
+    let main = tx.open_db(None).await?.dbi();
+    let Some(v) = tx.get::<Vec<u8>>(main, key).await? else {
+        return Ok(None);
+    };
+    let is_table = match is_table_tx(tx, key)? {
+        Some(is_table) => is_table,
+        None => {
+            std::str::from_utf8(key).is_ok_and(|name| D::schema_fingerprints().contains_key(name))
+        }
+    };
+    Ok((!is_table).then_some((main, v)))
+}
+
+/// Counts the duplicates of `key` a page at a time, `cur` must be positioned on it and every
+/// duplicate be `size` bytes long.
+async fn count_dup_pages<K: TransactionKind>(
+    cur: &mut CursorAny<K>,
+    key: &[u8],
+    size: usize,
+) -> Result<usize, ClientError> {
+    let mut count = cur
+        .get_multiple::<Vec<u8>>()
+        .await?
+        .map_or(0, |p| p.len() / size);
+    while let Some((k, page)) = cur.next_multiple::<Vec<u8>, Vec<u8>>().await? {
+        if k != key {
+            break;
+        }
+        count += page.len() / size;
+    }
+    Ok(count)
+}
+
+fn table_metadata_key<T: MDBXTable>() -> &'static [u8] {
+    T::NAME.unwrap_or_default().as_bytes()
+}
+
+/// Selects a table of a tuple list of tables by type and name at runtime, superseded by
+// `TableIndex`, which does it at compile time.
+#[deprecated(note = "select tables with `TableIndex`, e.g. `HasMDBXTableDbis::table::<T, _>`")]
 pub trait MatchName {
     fn match_name<T>(&self, name: Option<&str>) -> Option<&T>;
     fn match_name_mut<T>(&mut self, name: Option<&str>) -> Option<&mut T>;
 }
 
-pub trait MDBXTables<E> {
-    fn create_all(
-        tx: &libmdbx_remote::TransactionAny<RW>,
-        flags: DatabaseFlags,
-    ) -> impl Future<Output = Result<HashMap<String, u32>, E>> + Send;
-}
-
+#[allow(deprecated)]
 impl MatchName for () {
     fn match_name<T>(&self, _name: Option<&str>) -> Option<&T> {
         None
@@ -33,6 +114,7 @@ impl MatchName for () {
     }
 }
 
+#[allow(deprecated)]
 impl<Head, Tail> MatchName for (Head, Tail)
 where
     Head: MDBXTable,
@@ -55,6 +137,101 @@ where
     }
 }
 
+// Positions in a tuple list, inferred when selecting a table by type.
+pub struct Here;
+pub struct There<I>(PhantomData<I>);
+
+/// An entry of `HasMDBXTableDbis::DbiTables`, the table `T` accessed as `A`.
+pub struct DbiTable<T, A>(PhantomData<fn() -> (T, A)>);
+
+/// How a database accesses a table, `Mode` is the mode of the table handles of a database in
+/// mode `M`. Tables the database owns are written in its own mode, `read_only` ones never.
+pub trait TableAccess {
+    type Mode<M: TransactionKind>: TransactionKind;
+}
+
+pub struct Owned;
+pub struct Shared;
+
+impl TableAccess for Owned {
+    type Mode<M: TransactionKind> = M;
+}
+
+impl TableAccess for Shared {
+    type Mode<M: TransactionKind> = RO;
+}
+
+/// The table `T` created with the `DatabaseFlags` bits `FLAGS` besides its own, as listed in the
+/// owned tables of a database that gives a table `flags`. Creating, fingerprinting and checking
+/// it use the combined flags.
+pub struct WithFlags<T, const FLAGS: u32>(PhantomData<fn() -> T>);
+
+impl<T: MDBXTable, const FLAGS: u32> MDBXTable for WithFlags<T, FLAGS> {
+    type Key = T::Key;
+    type Value = T::Value;
+    type Error = T::Error;
+    type Metadata = T::Metadata;
+
+    const NAME: Option<&'static str> = T::NAME;
+    const DUPSORT: bool = T::DUPSORT;
+    const FLAGS: DatabaseFlags = T::FLAGS.union(DatabaseFlags::from_bits_retain(FLAGS as _));
+}
+
+/// Implemented by the tuple lists that contain `T`, at position `INDEX`. Selecting a table that
+/// is not in the list fails to compile, as does selecting one listed twice.
+#[diagnostic::on_unimplemented(message = "`{T}` is not a table of this database")]
+pub trait TableIndex<T, I> {
+    const INDEX: usize;
+    type Access: TableAccess;
+}
+
+impl<T, A: TableAccess, Tail> TableIndex<T, Here> for (DbiTable<T, A>, Tail) {
+    const INDEX: usize = 0;
+    type Access = A;
+}
+
+impl<T, Head, Tail, I> TableIndex<T, There<I>> for (Head, Tail)
+where
+    Tail: TableIndex<T, I>,
+{
+    const INDEX: usize = Tail::INDEX + 1;
+    type Access = Tail::Access;
+}
+
+/// Generic logic run against every table of a database by `MDBXTables::visit_all`, the table
+/// is given as `T`, with its name, flags and typed key and value. Only the owned tables are
+/// visited, `read_only` and `optional` ones are not, so `T::FLAGS` are the flags the table was
+/// created with.
+pub trait TableVisitor<E> {
+    fn visit<T>(&mut self) -> impl Future<Output = Result<(), E>> + Send
+    where
+        T: MDBXTable,
+        E: From<T::Error>;
+}
+
+pub trait MDBXTables<E> {
+    fn create_all(
+        tx: &libmdbx_remote::TransactionAny<RW>,
+        flags: DatabaseFlags,
+    ) -> impl Future<Output = Result<HashMap<String, u32>, E>> + Send;
+    fn stats_all<K: TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+    ) -> impl Future<Output = Result<HashMap<String, TableStat>, E>> + Send;
+    fn clear_all(
+        tx: &libmdbx_remote::TransactionAny<RW>,
+    ) -> impl Future<Output = Result<(), E>> + Send;
+    /// # Safety
+    /// The same as `MDBXTable::drop_table_tx`, for every table.
+    unsafe fn drop_all(
+        tx: &libmdbx_remote::TransactionAny<RW>,
+    ) -> impl Future<Output = Result<(), E>> + Send;
+    fn fingerprints(out: &mut Fingerprints);
+    /// Visits the tables in order and stops at the first error.
+    fn visit_all<V: TableVisitor<E> + Send>(
+        visitor: &mut V,
+    ) -> impl Future<Output = Result<(), E>> + Send;
+}
+
 impl<E> MDBXTables<E> for () {
     async fn create_all(
         _tx: &libmdbx_remote::TransactionAny<RW>,
@@ -62,6 +239,26 @@ impl<E> MDBXTables<E> for () {
     ) -> Result<HashMap<String, u32>, E> {
         Ok(HashMap::new())
     }
+
+    async fn stats_all<K: TransactionKind>(
+        _tx: &libmdbx_remote::TransactionAny<K>,
+    ) -> Result<HashMap<String, TableStat>, E> {
+        Ok(HashMap::new())
+    }
+
+    async fn clear_all(_tx: &libmdbx_remote::TransactionAny<RW>) -> Result<(), E> {
+        Ok(())
+    }
+
+    async unsafe fn drop_all(_tx: &libmdbx_remote::TransactionAny<RW>) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn fingerprints(_out: &mut Fingerprints) {}
+
+    async fn visit_all<V: TableVisitor<E> + Send>(_visitor: &mut V) -> Result<(), E> {
+        Ok(())
+    }
 }
 
 impl<Head, Tail, E> MDBXTables<E> for (Head, Tail)
@@ -75,11 +272,55 @@ where
         flags: DatabaseFlags,
     ) -> Result<HashMap<String, u32>, E> {
         let mut vals = HashMap::new();
-        let dbi = Head::create_table_tx(tx, flags).await?;
+        let exists = table_exists_tx(tx, Head::NAME)
+            .await
+            .map_err(Head::Error::from)?;
+        let dbi = if exists {
+            let dbi = Head::open_table_tx(tx).await?;
+            check_flags_tx(tx, Head::NAME, dbi, flags | Head::FLAGS).map_err(Head::Error::from)?;
+            dbi
+        } else {
+            Head::create_table_tx(tx, flags | Head::FLAGS).await?
+        };
         vals.insert(Head::NAME.map(|s| s.to_string()).unwrap_or_default(), dbi);
         vals.extend(Tail::create_all(tx, flags).await?);
         Ok(vals)
     }
+
+    async fn stats_all<K: TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+    ) -> Result<HashMap<String, TableStat>, E> {
+        let mut vals = HashMap::new();
+        let stat = Head::stat_tx(tx, None).await?;
+        vals.insert(Head::NAME.map(|s| s.to_string()).unwrap_or_default(), stat);
+        vals.extend(Tail::stats_all(tx).await?);
+        Ok(vals)
+    }
+
+    async fn clear_all(tx: &libmdbx_remote::TransactionAny<RW>) -> Result<(), E> {
+        Head::clear_tx(tx, None).await?;
+        Tail::clear_all(tx).await
+    }
+
+    async unsafe fn drop_all(tx: &libmdbx_remote::TransactionAny<RW>) -> Result<(), E> {
+        unsafe {
+            Head::drop_table_tx(tx).await?;
+            Tail::drop_all(tx).await
+        }
+    }
+
+    fn fingerprints(out: &mut Fingerprints) {
+        out.insert(
+            Head::NAME.map(|s| s.to_string()).unwrap_or_default(),
+            table_fingerprint::<Head>(),
+        );
+        Tail::fingerprints(out);
+    }
+
+    async fn visit_all<V: TableVisitor<E> + Send>(visitor: &mut V) -> Result<(), E> {
+        visitor.visit::<Head>().await?;
+        Tail::visit_all(visitor).await
+    }
 }
 
 pub trait MDBXTable: Sized {
@@ -89,6 +330,11 @@ pub trait MDBXTable: Sized {
     type Metadata: TableObjectEncode + TableObjectDecode + Send + Sync;
     const NAME: Option<&'static str>;
     const DUPSORT: bool = false;
+    const FLAGS: DatabaseFlags = if Self::DUPSORT {
+        DatabaseFlags::DUP_SORT
+    } else {
+        DatabaseFlags::empty()
+    };
 
     fn open_table_tx<T: libmdbx_remote::TransactionKind>(
         tx: &libmdbx_remote::TransactionAny<T>,
@@ -100,11 +346,29 @@ pub trait MDBXTable: Sized {
     }
 
     fn open_table(
-        env: &libmdbx_remote::EnvironmentAny,
+        env: &(impl HasMDBXEnvironment + Sync),
+    ) -> impl Future<Output = Result<u32, Self::Error>> + Send {
+        async {
+            if let Some(dbi) = env.cached_dbi::<Self>() {
+                return Ok(dbi);
+            }
+            let tx = env.env().begin_ro_txn().await?;
+            let dbi = Self::open_table_tx(&tx).await?;
+            env.cache_dbi::<Self>(dbi);
+            Ok(dbi)
+        }
+    }
+
+    /// Like `open_table_tx`, but fails with `FlagsMismatch` if the table was created with other
+    /// flags than `FLAGS`, which would make reads silently wrong. Databases check their tables
+    /// once when they open, the other helpers don't.
+    fn open_table_checked_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
     ) -> impl Future<Output = Result<u32, Self::Error>> + Send {
         async {
-            let tx = env.begin_ro_txn().await?;
-            Self::open_table_tx(&tx).await
+            let dbi = Self::open_table_tx(tx).await?;
+            check_table_flags_tx::<Self, _>(tx, dbi)?;
+            Ok(dbi)
         }
     }
 
@@ -119,22 +383,207 @@ pub trait MDBXTable: Sized {
     }
 
     fn create_table(
-        env: &libmdbx_remote::EnvironmentAny,
+        env: &(impl HasMDBXEnvironment<Mode = RW> + Sync),
         flags: libmdbx_remote::DatabaseFlags,
     ) -> impl Future<Output = Result<u32, Self::Error>> + Send {
         async move {
-            let tx = env.begin_rw_txn().await?;
-            Self::create_table_tx(&tx, flags).await
+            let tx = WriteTxn::begin(env.env()).await?;
+            let result = Self::create_table_tx(&tx, flags).await;
+            let dbi = tx.finish(result).await?;
+            if flags.bits() == Self::FLAGS.bits() {
+                env.cache_dbi::<Self>(dbi);
+            }
+            Ok(dbi)
+        }
+    }
+
+    fn stat(
+        env: &(impl HasMDBXEnvironment + Sync),
+    ) -> impl Future<Output = Result<TableStat, Self::Error>> + Send {
+        async move {
+            let tx = env.env().begin_ro_txn().await?;
+            let dbi = Self::open_table_cached_tx(env, &tx).await?;
+            Self::stat_tx(&tx, Some(dbi)).await
+        }
+    }
+
+    fn stat_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+        dbi: Option<u32>,
+    ) -> impl Future<Output = Result<TableStat, Self::Error>> + Send {
+        async move {
+            let dbi = if let Some(dbi) = dbi {
+                dbi
+            } else {
+                Self::open_table_tx(tx).await?
+            };
+            Ok(tx.db_stat_with_dbi(dbi).await?.into())
+        }
+    }
+
+    fn clear(
+        env: &(impl HasMDBXEnvironment<Mode = RW> + Sync),
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async move {
+            let tx = WriteTxn::begin(env.env()).await?;
+            let result: Result<_, Self::Error> = async {
+                let dbi = Self::open_table_cached_tx(env, &tx).await?;
+                Self::clear_tx(&tx, Some(dbi)).await?;
+                Ok(())
+            }
+            .await;
+            tx.finish(result).await?;
+            Ok(())
+        }
+    }
+
+    /// Deletes all items but keeps the table.
+    fn clear_tx(
+        tx: &libmdbx_remote::TransactionAny<libmdbx_remote::RW>,
+        dbi: Option<u32>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async move {
+            let dbi = if let Some(dbi) = dbi {
+                dbi
+            } else {
+                Self::open_table_tx(tx).await?
+            };
+            tx.clear_db(dbi).await?;
+            Ok(())
+        }
+    }
+
+    /// # Safety
+    /// The same as `drop_table_tx`.
+    unsafe fn drop_table(
+        env: &(impl HasMDBXEnvironment<Mode = RW> + Sync),
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async move {
+            let tx = WriteTxn::begin(env.env()).await?;
+            let result = unsafe { Self::drop_table_tx(&tx) }.await;
+            tx.finish(result).await?;
+            if let Some(cache) = env.dbi_cache() {
+                cache.remove::<Self>();
+            }
+            Ok(())
+        }
+    }
+
+    /// Deletes the table, if it exists, and closes its DBI once `tx` commits. Only local
+    /// environments can drop tables.
+    ///
+    /// # Safety
+    /// MDBX may hand the closed DBI number to the next table opened, so no DBI of the table
+    /// may be used afterwards, whether in a `*Dbi` struct, a `TableHandle`, a `DbiCache` or a
+    /// cursor, of any database on the environment. Databases using the table must be opened
+    /// again.
+    unsafe fn drop_table_tx(
+        tx: &libmdbx_remote::TransactionAny<libmdbx_remote::RW>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async move {
+            if !matches!(tx, libmdbx_remote::TransactionAny::Local(_)) {
+                return Err(MDBXDeriveError::RemoteUnsupported("dropping a table").into());
+            }
+            // Opening a missing table would fail the transaction
+            if !table_exists_tx(tx, Self::NAME).await? {
+                return Ok(());
+            }
+            let (
+                libmdbx_remote::TransactionAny::Local(local),
+                libmdbx_remote::DatabaseAny::Local(db),
+            ) = (tx, tx.open_db(Self::NAME).await?)
+            else {
+                unreachable!("local transactions open local databases");
+            };
+            // SAFETY: the caller upholds that no other handle of the table is used
+            unsafe { local.drop_db(db) }.map_err(MDBXDeriveError::from)?;
+            if let Some(dbi) = open_reserved_table_tx(tx, TABLE_METADATA_NAME).await? {
+                tx.del(dbi, table_metadata_key::<Self>(), None).await?;
+            }
+            Ok(())
+        }
+    }
+
+    fn table_metadata(
+        env: &(impl HasMDBXEnvironment + Sync),
+    ) -> impl Future<Output = Result<Option<Self::Metadata>, Self::Error>> + Send {
+        async move {
+            let tx = env.env().begin_ro_txn().await?;
+            Self::table_metadata_tx(&tx).await
+        }
+    }
+
+    fn table_metadata_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+    ) -> impl Future<Output = Result<Option<Self::Metadata>, Self::Error>> + Send {
+        async move {
+            let Some(dbi) = open_reserved_table_tx(tx, TABLE_METADATA_NAME).await? else {
+                return Ok(None);
+            };
+            Ok(tx
+                .get::<Vec<u8>>(dbi, table_metadata_key::<Self>())
+                .await?
+                .map(|v| Self::Metadata::table_decode(&v))
+                .transpose()?)
+        }
+    }
+
+    fn write_table_metadata(
+        env: &(impl HasMDBXEnvironment<Mode = RW> + Sync),
+        meta: &Self::Metadata,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async move {
+            let tx = WriteTxn::begin(env.env()).await?;
+            let result = Self::write_table_metadata_tx(&tx, meta).await;
+            tx.finish(result).await?;
+            Ok(())
+        }
+    }
+
+    /// Creates the table metadata table if needed.
+    fn write_table_metadata_tx(
+        tx: &libmdbx_remote::TransactionAny<libmdbx_remote::RW>,
+        meta: &Self::Metadata,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async move {
+            let dbi = tx
+                .create_db(Some(TABLE_METADATA_NAME), DatabaseFlags::empty())
+                .await?
+                .dbi();
+            tx.put(
+                dbi,
+                table_metadata_key::<Self>(),
+                &meta.table_encode()?,
+                WriteFlags::default(),
+            )
+            .await?;
+            Ok(())
         }
     }
 
     fn get_item(
-        env: &libmdbx_remote::EnvironmentAny,
+        env: &(impl HasMDBXEnvironment + Sync),
         key: &Self::Key,
     ) -> impl Future<Output = Result<Option<Self::Value>, Self::Error>> + Send {
         async move {
-            let tx = env.begin_ro_txn().await?;
-            Self::get_item_tx(&tx, None, key).await
+            let tx = env.env().begin_ro_txn().await?;
+            let dbi = Self::open_table_cached_tx(env, &tx).await?;
+            Self::get_item_tx(&tx, Some(dbi), key).await
+        }
+    }
+
+    /// Opens the table with `tx` unless `env` has its handle cached already.
+    fn open_table_cached_tx<K: libmdbx_remote::TransactionKind>(
+        env: &(impl HasMDBXEnvironment + Sync),
+        tx: &libmdbx_remote::TransactionAny<K>,
+    ) -> impl Future<Output = Result<u32, Self::Error>> + Send {
+        async move {
+            if let Some(dbi) = env.cached_dbi::<Self>() {
+                return Ok(dbi);
+            }
+            let dbi = Self::open_table_tx(tx).await?;
+            env.cache_dbi::<Self>(dbi);
+            Ok(dbi)
         }
     }
 
@@ -160,14 +609,26 @@ pub trait MDBXTable: Sized {
     }
 
     fn put_item(
-        env: &libmdbx_remote::EnvironmentAny,
+        env: &(impl HasMDBXEnvironment<Mode = RW> + Sync),
         key: &Self::Key,
         value: &Self::Value,
         flags: libmdbx_remote::WriteFlags,
     ) -> impl Future<Output = Result<(), Self::Error>> + Send {
         async move {
-            let tx = env.begin_rw_txn().await?;
-            Self::put_item_tx(&tx, None, key, value, flags).await
+            let tx = WriteTxn::begin(env.env()).await?;
+            let result: Result<_, Self::Error> = async {
+                let dbi = match env.cached_dbi::<Self>() {
+                    Some(dbi) => dbi,
+                    None => Self::create_table_tx(&tx, Self::FLAGS).await?,
+                };
+                Self::put_item_tx(&tx, Some(dbi), key, value, flags).await?;
+                Ok(dbi)
+            }
+            .await;
+            let dbi = tx.finish(result).await?;
+            // The handle of a table created by `tx` is valid only after the commit
+            env.cache_dbi::<Self>(dbi);
+            Ok(())
         }
     }
 
@@ -182,100 +643,1021 @@ pub trait MDBXTable: Sized {
             let dbi = if let Some(dbi) = dbi {
                 dbi
             } else {
-                Self::create_table_tx(tx, libmdbx_remote::DatabaseFlags::default()).await?
+                Self::create_table_tx(tx, Self::FLAGS).await?
             };
             tx.put(dbi, &key.key_encode()?, &value.table_encode()?, flags)
                 .await?;
             Ok(())
         }
     }
-}
 
-pub trait HasMDBXEnvironment {
-    fn env(&self) -> &EnvironmentAny;
-}
+    fn get_many(
+        env: &(impl HasMDBXEnvironment + Sync),
+        keys: impl IntoIterator<Item = impl Borrow<Self::Key>>,
+    ) -> impl Future<Output = Result<Vec<Option<Self::Value>>, Self::Error>> + Send {
+        let keys = encode_keys::<Self>(keys);
+        async move {
+            let keys = keys?;
+            let tx = env.env().begin_ro_txn().await?;
+            let dbi = Self::open_table_cached_tx(env, &tx).await?;
+            Ok(get_many_raw::<Self, _>(&tx, dbi, keys).await?)
+        }
+    }
+
+    /// Values are returned in the order of `keys`.
+    fn get_many_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+        dbi: Option<u32>,
+        keys: impl IntoIterator<Item = impl Borrow<Self::Key>>,
+    ) -> impl Future<Output = Result<Vec<Option<Self::Value>>, Self::Error>> + Send {
+        let keys = encode_keys::<Self>(keys);
+        async move {
+            let keys = keys?;
+            let dbi = if let Some(dbi) = dbi {
+                dbi
+            } else {
+                Self::open_table_tx(tx).await?
+            };
+            Ok(get_many_raw::<Self, _>(tx, dbi, keys).await?)
+        }
+    }
+
+    fn put_many(
+        env: &(impl HasMDBXEnvironment<Mode = RW> + Sync),
+        items: impl IntoIterator<Item = (impl Borrow<Self::Key>, impl Borrow<Self::Value>)>,
+        flags: libmdbx_remote::WriteFlags,
+    ) -> impl Future<Output = Result<usize, Self::Error>> + Send {
+        let items = encode_items::<Self>(items);
+        async move {
+            let items = items?;
+            let tx = WriteTxn::begin(env.env()).await?;
+            let result: Result<_, Self::Error> = async {
+                let dbi = match env.cached_dbi::<Self>() {
+                    Some(dbi) => dbi,
+                    None => Self::create_table_tx(&tx, Self::FLAGS).await?,
+                };
+                let written = put_many_raw::<Self>(&tx, dbi, items, flags).await?;
+                Ok((dbi, written))
+            }
+            .await;
+            let (dbi, written) = tx.finish(result).await?;
+            env.cache_dbi::<Self>(dbi);
+            Ok(written)
+        }
+    }
+
+    /// Sorts the items and appends them if they all go past the end of the table, returns
+    /// the number of items written once repeated keys are dropped.
+    fn put_many_tx(
+        tx: &libmdbx_remote::TransactionAny<libmdbx_remote::RW>,
+        dbi: Option<u32>,
+        items: impl IntoIterator<Item = (impl Borrow<Self::Key>, impl Borrow<Self::Value>)>,
+        flags: libmdbx_remote::WriteFlags,
+    ) -> impl Future<Output = Result<usize, Self::Error>> + Send {
+        let items = encode_items::<Self>(items);
+        async move {
+            let items = items?;
+            let dbi = if let Some(dbi) = dbi {
+                dbi
+            } else {
+                Self::create_table_tx(tx, Self::FLAGS).await?
+            };
+            Ok(put_many_raw::<Self>(tx, dbi, items, flags).await?)
+        }
+    }
+
+    fn delete_many(
+        env: &(impl HasMDBXEnvironment<Mode = RW> + Sync),
+        keys: impl IntoIterator<Item = impl Borrow<Self::Key>>,
+    ) -> impl Future<Output = Result<usize, Self::Error>> + Send {
+        let keys = encode_keys::<Self>(keys);
+        async move {
+            let keys = keys?;
+            let tx = WriteTxn::begin(env.env()).await?;
+            let result: Result<_, Self::Error> = async {
+                let dbi = Self::open_table_cached_tx(env, &tx).await?;
+                Ok(delete_many_raw(&tx, dbi, keys).await?)
+            }
+            .await;
+            tx.finish(result).await
+        }
+    }
+
+    /// Deletes the keys with all their duplicates, returns the number of keys that existed.
+    fn delete_many_tx(
+        tx: &libmdbx_remote::TransactionAny<libmdbx_remote::RW>,
+        dbi: Option<u32>,
+        keys: impl IntoIterator<Item = impl Borrow<Self::Key>>,
+    ) -> impl Future<Output = Result<usize, Self::Error>> + Send {
+        let keys = encode_keys::<Self>(keys);
+        async move {
+            let keys = keys?;
+            let dbi = if let Some(dbi) = dbi {
+                dbi
+            } else {
+                Self::open_table_tx(tx).await?
+            };
+            Ok(delete_many_raw(tx, dbi, keys).await?)
+        }
+    }
+
+    fn cursor_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+        dbi: Option<u32>,
+    ) -> impl Future<Output = Result<TableCursor<Self, K>, Self::Error>> + Send {
+        async move {
+            let dbi = if let Some(dbi) = dbi {
+                dbi
+            } else {
+                Self::open_table_tx(tx).await?
+            };
+            Ok(TableCursor::new(tx.cursor_with_dbi(dbi).await?))
+        }
+    }
+
+    fn range_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+        dbi: Option<u32>,
+        bounds: impl RangeBounds<Self::Key>,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<TableItem<Self>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send {
+        let bounds = encode_range(&bounds);
+        async move {
+            let (lower, upper) = bounds?;
+            Ok(Self::cursor_tx(tx, dbi)
+                .await?
+                .into_range_stream(lower, upper, false))
+        }
+    }
+
+    fn range_rev_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+        dbi: Option<u32>,
+        bounds: impl RangeBounds<Self::Key>,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<TableItem<Self>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send {
+        let bounds = encode_range(&bounds);
+        async move {
+            let (lower, upper) = bounds?;
+            Ok(Self::cursor_tx(tx, dbi)
+                .await?
+                .into_range_stream(lower, upper, true))
+        }
+    }
+
+    /// Scans the items whose encoded key starts with `prefix`.
+    fn prefix_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+        dbi: Option<u32>,
+        prefix: &[u8],
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<TableItem<Self>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send {
+        let (lower, upper) = (Bound::Included(prefix.to_vec()), prefix_end(prefix));
+        async move {
+            Ok(Self::cursor_tx(tx, dbi)
+                .await?
+                .into_range_stream(lower, upper, false))
+        }
+    }
+
+    fn prefix_rev_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+        dbi: Option<u32>,
+        prefix: &[u8],
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<TableItem<Self>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send {
+        let (lower, upper) = (Bound::Included(prefix.to_vec()), prefix_end(prefix));
+        async move {
+            Ok(Self::cursor_tx(tx, dbi)
+                .await?
+                .into_range_stream(lower, upper, true))
+        }
+    }
+
+    /// Reads like `get_item_tx` and rewrites the item in the primary format if it was
+    /// decoded with a legacy one.
+    fn get_item_migrate_tx(
+        tx: &libmdbx_remote::TransactionAny<libmdbx_remote::RW>,
+        dbi: Option<u32>,
+        key: &Self::Key,
+    ) -> impl Future<Output = Result<Option<Self::Value>, Self::Error>> + Send
+    where
+        Self::Value: SniffedObject,
+    {
+        async move {
+            let dbi = if let Some(dbi) = dbi {
+                dbi
+            } else {
+                Self::open_table_tx(tx).await?
+            };
+            let key = key.key_encode()?;
+            let Some(raw) = tx.get::<Vec<u8>>(dbi, &key).await? else {
+                return Ok(None);
+            };
+            let v = Self::Value::table_decode(&raw)?;
+            if v.is_legacy() {
+                if Self::DUPSORT {
+                    tx.del(dbi, &key, Some(&raw)).await?;
+                }
+                tx.put(dbi, &key, &v.table_encode()?, WriteFlags::default())
+                    .await?;
+            }
+            Ok(Some(v))
+        }
+    }
+
+    /// Rewrites every item stored in a legacy format, returns the number of rewritten items.
+    fn migrate_items_tx(
+        tx: &libmdbx_remote::TransactionAny<libmdbx_remote::RW>,
+        dbi: Option<u32>,
+    ) -> impl Future<Output = Result<usize, Self::Error>> + Send
+    where
+        Self::Value: SniffedObject,
+    {
+        async move {
+            let dbi = if let Some(dbi) = dbi {
+                dbi
+            } else {
+                Self::open_table_tx(tx).await?
+            };
+            let mut cur = tx.cursor_with_dbi(dbi).await?;
+            let mut item = cur.first::<Vec<u8>, Vec<u8>>().await?;
+            let mut migrated = 0;
+            // A new encoding moves the item within its duplicates, so dup items are
+            // rewritten after the scan instead of in place.
+            let mut dups = vec![];
+            while let Some((key, raw)) = item {
+                let v = Self::Value::table_decode(&raw)?;
+                if v.is_legacy() {
+                    let encoded = v.table_encode()?;
+                    if Self::DUPSORT {
+                        dups.push((key, raw, encoded));
+                    } else {
+                        cur.put(&key, &encoded, WriteFlags::CURRENT).await?;
+                    }
+                    migrated += 1;
+                }
+                item = cur.next::<Vec<u8>, Vec<u8>>().await?;
+            }
+            for (key, raw, encoded) in dups {
+                tx.del(dbi, &key, Some(&raw)).await?;
+                tx.put(dbi, &key, &encoded, WriteFlags::default()).await?;
+            }
+            Ok(migrated)
+        }
+    }
+
+    fn migrate_items(
+        env: &(impl HasMDBXEnvironment<Mode = RW> + Sync),
+    ) -> impl Future<Output = Result<usize, Self::Error>> + Send
+    where
+        Self::Value: SniffedObject,
+    {
+        async move {
+            let tx = WriteTxn::begin(env.env()).await?;
+            let result = Self::migrate_items_tx(&tx, env.cached_dbi::<Self>()).await;
+            tx.finish(result).await
+        }
+    }
+}
+
+/// Typed access to the duplicates of a DUPSORT table, values are compared by their encoding.
+pub trait MDBXDupSortTable: MDBXTable {
+    fn get_dups(
+        env: &(impl HasMDBXEnvironment + Sync),
+        key: &Self::Key,
+    ) -> impl Future<Output = Result<Vec<Self::Value>, Self::Error>> + Send {
+        async move {
+            let tx = env.env().begin_ro_txn().await?;
+            let dbi = Self::open_table_cached_tx(env, &tx).await?;
+            Self::get_dups_tx(&tx, Some(dbi), key).await
+        }
+    }
+
+    fn get_dups_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+        dbi: Option<u32>,
+        key: &Self::Key,
+    ) -> impl Future<Output = Result<Vec<Self::Value>, Self::Error>> + Send {
+        async move {
+            let mut cur = Self::cursor_tx(tx, dbi).await?;
+            let mut vals = vec![];
+            let mut item = cur.seek_exact(key).await?;
+            while let Some((_, v)) = item {
+                vals.push(v);
+                item = cur.next_dup().await?;
+            }
+            Ok(vals)
+        }
+    }
+
+    fn count_dups_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+        dbi: Option<u32>,
+        key: &Self::Key,
+    ) -> impl Future<Output = Result<usize, Self::Error>> + Send {
+        async move {
+            let dbi = if let Some(dbi) = dbi {
+                dbi
+            } else {
+                Self::open_table_tx(tx).await?
+            };
+            let mut cur = tx.cursor_with_dbi(dbi).await?;
+            let key = key.key_encode()?;
+            let Some(first) = cur.set::<Vec<u8>>(&key).await? else {
+                return Ok(0);
+            };
+            if let (TransactionAny::Local(tx), CursorAny::Local(cur)) = (tx, &cur) {
+                let mut count = 0;
+                // SAFETY: the cursor is open and positioned, and used under the lock of its
+                // transaction
+                let rc = tx
+                    .txn_execute(|_| unsafe {
+                        libmdbx_remote::ffi::mdbx_cursor_count(cur.cursor(), &mut count)
+                    })
+                    .map_err(ClientError::MDBX)?;
+                if rc != 0 {
+                    return Err(ClientError::MDBX(libmdbx_remote::Error::from_err_code(rc)).into());
+                }
+                return Ok(count);
+            }
+            // The remote protocol has no count call. DUP_FIXED duplicates all have the size of
+            // the first one and are counted a page at a time, others one round trip each.
+            if Self::FLAGS.contains(DatabaseFlags::DUP_FIXED) {
+                return Ok(count_dup_pages(&mut cur, &key, first.len()).await?);
+            }
+            let mut count = 1;
+            while cur.next_dup::<Vec<u8>, Vec<u8>>().await?.is_some() {
+                count += 1;
+            }
+            Ok(count)
+        }
+    }
+
+    fn first_dup_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+        dbi: Option<u32>,
+        key: &Self::Key,
+    ) -> impl Future<Output = Result<Option<Self::Value>, Self::Error>> + Send {
+        async move {
+            let mut cur = Self::cursor_tx(tx, dbi).await?;
+            Ok(cur.seek_exact(key).await?.map(|(_, v)| v))
+        }
+    }
+
+    fn last_dup_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+        dbi: Option<u32>,
+        key: &Self::Key,
+    ) -> impl Future<Output = Result<Option<Self::Value>, Self::Error>> + Send {
+        async move {
+            let mut cur = Self::cursor_tx(tx, dbi).await?;
+            if cur.seek_exact(key).await?.is_none() {
+                return Ok(None);
+            }
+            cur.last_dup().await
+        }
+    }
+
+    /// Returns the duplicate equal to `value` if it exists.
+    fn get_both_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+        dbi: Option<u32>,
+        key: &Self::Key,
+        value: &Self::Value,
+    ) -> impl Future<Output = Result<Option<Self::Value>, Self::Error>> + Send {
+        async move {
+            let mut cur = Self::cursor_tx(tx, dbi).await?;
+            cur.get_both(key, value).await
+        }
+    }
+
+    /// Returns the first duplicate greater than or equal to `value`.
+    fn get_both_range_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+        dbi: Option<u32>,
+        key: &Self::Key,
+        value: &Self::Value,
+    ) -> impl Future<Output = Result<Option<Self::Value>, Self::Error>> + Send {
+        async move {
+            let mut cur = Self::cursor_tx(tx, dbi).await?;
+            cur.get_both_range(key, value).await
+        }
+    }
+
+    fn del_dup_tx(
+        tx: &libmdbx_remote::TransactionAny<libmdbx_remote::RW>,
+        dbi: Option<u32>,
+        key: &Self::Key,
+        value: &Self::Value,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send {
+        async move {
+            let dbi = if let Some(dbi) = dbi {
+                dbi
+            } else {
+                Self::open_table_tx(tx).await?
+            };
+            Ok(tx
+                .del(dbi, &key.key_encode()?, Some(&value.table_encode()?))
+                .await?)
+        }
+    }
+}
+
+pub trait MDBXDupFixedTable: MDBXDupSortTable<Value: FixedSizeObject> {
+    /// Reads all duplicates of `key` a page at a time.
+    fn get_dups_multiple_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+        dbi: Option<u32>,
+        key: &Self::Key,
+    ) -> impl Future<Output = Result<Vec<Self::Value>, Self::Error>> + Send {
+        async move {
+            let mut cur = Self::cursor_tx(tx, dbi).await?;
+            if cur.seek_exact(key).await?.is_none() {
+                return Ok(vec![]);
+            }
+            let mut vals = cur.get_multiple().await?.unwrap_or_default();
+            let key = key.key_encode()?;
+            while let Some((k, page)) = cur.next_multiple_raw().await? {
+                if k != key {
+                    break;
+                }
+                vals.extend(page);
+            }
+            Ok(vals)
+        }
+    }
+
+    /// Like `count_dups_tx`, but counts a page of duplicates at a time.
+    fn count_dups_multiple_tx<K: libmdbx_remote::TransactionKind>(
+        tx: &libmdbx_remote::TransactionAny<K>,
+        dbi: Option<u32>,
+        key: &Self::Key,
+    ) -> impl Future<Output = Result<usize, Self::Error>> + Send {
+        async move {
+            let dbi = if let Some(dbi) = dbi {
+                dbi
+            } else {
+                Self::open_table_tx(tx).await?
+            };
+            let mut cur = tx.cursor_with_dbi(dbi).await?;
+            let key = key.key_encode()?;
+            if cur.set::<Vec<u8>>(&key).await?.is_none() {
+                return Ok(0);
+            }
+            Ok(count_dup_pages(&mut cur, &key, Self::Value::FIXED_SIZE).await?)
+        }
+    }
+}
+
+pub trait HasMDBXEnvironment {
+    /// `RO` for handles that must not write, which the write methods of tables and databases
+    /// require to be `RW`.
+    type Mode: TransactionKind;
+
+    fn env(&self) -> &EnvironmentAny;
+
+    fn dbi_cache(&self) -> Option<&DbiCache> {
+        None
+    }
+
+    fn cached_dbi<T: MDBXTable>(&self) -> Option<u32> {
+        self.dbi_cache()?.get::<T>()
+    }
+
+    fn cache_dbi<T: MDBXTable>(&self, dbi: u32) {
+        if let Some(cache) = self.dbi_cache() {
+            cache.insert::<T>(dbi);
+        }
+    }
+}
+
+impl HasMDBXEnvironment for EnvironmentAny {
+    type Mode = RW;
+
+    fn env(&self) -> &EnvironmentAny {
+        self
+    }
+
+    /// Local environments share one cache between all their handles, remote ones have none.
+    fn dbi_cache(&self) -> Option<&DbiCache> {
+        self.as_local().map(local_dbi_cache)
+    }
+}
+
+// DBIs looked up by table name, superseded by the typed DBIs of `HasMDBXTableDbis`.
+#[deprecated(note = "use `HasMDBXTableDbis::table_dbi` with the `TableIndex` of the table")]
+pub trait HasMDBXDBIStore {
+    fn dbis(&self) -> &HashMap<String, u32>;
+
+    fn dbi<T: MDBXTable>(&self) -> Option<u32> {
+        self.dbis()
+            .get(&T::NAME.map(|v| v.to_string()).unwrap_or_default())
+            .copied()
+    }
+}
+
+pub trait HasMDBXTables {
+    type Error: From<libmdbx_remote::ClientError> + From<MDBXDeriveError> + Send + 'static;
+    type Tables: MDBXTables<Self::Error>;
+}
+
+/// The mode of the handle of `T` in the database `D`.
+pub type TableMode<D, T, I> =
+    <<<D as HasMDBXTableDbis>::DbiTables as TableIndex<T, I>>::Access as TableAccess>::Mode<
+        <D as HasMDBXTableDbis>::Mode,
+    >;
+
+/// The mode of the handle of the `optional` table `T` in the database `D`.
+pub type OptionalTableMode<D, T, I> = <<<D as HasMDBXTableDbis>::OptionalDbiTables as TableIndex<
+    T,
+    I,
+>>::Access as TableAccess>::Mode<<D as HasMDBXTableDbis>::Mode>;
+
+/// Type-indexed access to the tables of a database, e.g. `db.get::<Accounts, _>(&tx, &key)`.
+/// The `_` is the position of the table in `DbiTables`, which the compiler infers. Optional
+/// tables may have no DBI and are only available through `optional_table` and their accessors.
+pub trait HasMDBXTableDbis: HasMDBXTables {
+    type Mode: TransactionKind;
+    /// The owned and `read_only` tables, as a tuple list of `DbiTable`.
+    type DbiTables;
+    /// The `optional` tables, as a tuple list of `DbiTable`.
+    type OptionalDbiTables;
+
+    /// The DBI of the table at `index` of `DbiTables`.
+    fn table_dbi(&self, index: usize) -> u32;
+
+    /// The DBI of the table at `index` of `OptionalDbiTables`, if it existed when the database
+    /// opened.
+    fn optional_table_dbi(&self, index: usize) -> Option<u32>;
+
+    fn optional_table<T: MDBXTable, I>(
+        &self,
+    ) -> Option<TableHandle<T, OptionalTableMode<Self, T, I>>>
+    where
+        Self::OptionalDbiTables: TableIndex<T, I>,
+    {
+        self.optional_table_dbi(<Self::OptionalDbiTables as TableIndex<T, I>>::INDEX)
+            .map(TableHandle::new)
+    }
+
+    /// Read-only tables have read-only handles, whatever the mode of the database.
+    fn table<T: MDBXTable, I>(&self) -> TableHandle<T, TableMode<Self, T, I>>
+    where
+        Self::DbiTables: TableIndex<T, I>,
+    {
+        TableHandle::new(self.table_dbi(<Self::DbiTables as TableIndex<T, I>>::INDEX))
+    }
+
+    fn get<T: MDBXTable, I>(
+        &self,
+        tx: &libmdbx_remote::TransactionAny<impl TransactionKind>,
+        key: &T::Key,
+    ) -> impl Future<Output = Result<Option<T::Value>, T::Error>> + Send
+    where
+        Self::DbiTables: TableIndex<T, I>,
+    {
+        let table = self.table::<T, I>();
+        async move { table.get(tx, key).await }
+    }
+
+    fn put<T: MDBXTable, I>(
+        &self,
+        tx: &libmdbx_remote::TransactionAny<RW>,
+        key: &T::Key,
+        value: &T::Value,
+        flags: WriteFlags,
+    ) -> impl Future<Output = Result<(), T::Error>> + Send
+    where
+        Self::DbiTables: TableIndex<T, I, Access = Owned>,
+        Self: HasMDBXTableDbis<Mode = RW>,
+    {
+        let table = self.table::<T, I>();
+        async move { table.put(tx, key, value, flags).await }
+    }
+
+    fn del<T: MDBXTable, I>(
+        &self,
+        tx: &libmdbx_remote::TransactionAny<RW>,
+        key: &T::Key,
+        value: Option<&T::Value>,
+    ) -> impl Future<Output = Result<bool, T::Error>> + Send
+    where
+        Self::DbiTables: TableIndex<T, I, Access = Owned>,
+        Self: HasMDBXTableDbis<Mode = RW>,
+    {
+        let table = self.table::<T, I>();
+        async move { table.del(tx, key, value).await }
+    }
+
+    fn cursor<T: MDBXTable, I, K: TransactionKind>(
+        &self,
+        tx: &libmdbx_remote::TransactionAny<K>,
+    ) -> impl Future<Output = Result<TableCursor<T, K>, T::Error>> + Send
+    where
+        Self::DbiTables: TableIndex<T, I>,
+    {
+        let table = self.table::<T, I>();
+        async move { table.cursor(tx).await }
+    }
+}
+
+/// How `MDBXDatabase::write_with_retry` retries a failed write transaction. `transient`
+/// retries `Busy` right away, and `MapFull` once `grow_map` grew the map of a local environment.
+/// Waiting between retries needs a `sleep` of the async runtime in use.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy<E> {
+    pub retries: usize,
+    pub backoff: Duration,
+    pub sleep: Option<fn(Duration) -> BoxFuture<'static, ()>>,
+    pub is_transient: fn(&E) -> bool,
+    /// Errors retried after `grow_map` raised the upper size of the map.
+    pub is_map_full: fn(&E) -> bool,
+}
+
+impl<E> RetryPolicy<E> {
+    pub fn new(retries: usize, is_transient: fn(&E) -> bool) -> Self {
+        Self {
+            retries,
+            backoff: Duration::ZERO,
+            sleep: None,
+            is_transient,
+            is_map_full: |_| false,
+        }
+    }
+
+    /// Waits `backoff` with `sleep` before each retry, e.g.
+    /// `with_backoff(backoff, |d| Box::pin(tokio::time::sleep(d)))`.
+    pub fn with_backoff(
+        self,
+        backoff: Duration,
+        sleep: fn(Duration) -> BoxFuture<'static, ()>,
+    ) -> Self {
+        Self {
+            backoff,
+            sleep: Some(sleep),
+            ..self
+        }
+    }
+
+    pub fn with_map_growth(self, is_map_full: fn(&E) -> bool) -> Self {
+        Self {
+            is_map_full,
+            ..self
+        }
+    }
+}
+
+impl RetryPolicy<MDBXDeriveError> {
+    pub fn transient(retries: usize) -> Self {
+        Self::new(retries, MDBXDeriveError::is_transient)
+            .with_map_growth(MDBXDeriveError::is_map_full)
+    }
+}
+
+pub trait MDBXDatabase: Sized + Send + Sync + HasMDBXEnvironment + HasMDBXTables {
+    type Metadata: TableObjectEncode + TableObjectDecode + Send + Sync;
+    const METADATA_NAME: &'static [u8] = b"metadata";
+
+    fn create_all(
+        &self,
+        flags: DatabaseFlags,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send
+    where
+        Self: HasMDBXEnvironment<Mode = RW>,
+    {
+        async move {
+            let tx = WriteTxn::begin(self.env()).await?;
+            let result: Result<_, Self::Error> = async {
+                Self::Tables::create_all(&tx, flags).await?;
+                self.migrate_metadata_tx(&tx).await?;
+                self.check_schema_tx(&tx).await?;
+                self.write_schema_fingerprints_tx(&tx).await?;
+                Ok(())
+            }
+            .await;
+            tx.finish(result).await?;
+            Ok(())
+        }
+    }
+
+    fn schema_fingerprints() -> Fingerprints {
+        let mut fingerprints = Fingerprints::new();
+        Self::Tables::fingerprints(&mut fingerprints);
+        fingerprints
+    }
+
+    /// Changes whenever a table is added or removed, or its name, flags, key size or value
+    /// codec changes.
+    fn schema_fingerprint() -> u64 {
+        combined_fingerprint(&Self::schema_fingerprints())
+    }
+
+    fn write_schema_fingerprints_tx(
+        &self,
+        tx: &libmdbx_remote::TransactionAny<RW>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send
+    where
+        Self: HasMDBXEnvironment<Mode = RW>,
+    {
+        async move { Ok(write_fingerprints_tx(tx, &Self::schema_fingerprints()).await?) }
+    }
+
+    fn write_schema_fingerprints(&self) -> impl Future<Output = Result<(), Self::Error>> + Send
+    where
+        Self: HasMDBXEnvironment<Mode = RW>,
+    {
+        async move {
+            let tx = WriteTxn::begin(self.env()).await?;
+            let result = self.write_schema_fingerprints_tx(&tx).await;
+            tx.finish(result).await?;
+            Ok(())
+        }
+    }
+
+    /// Fails with `SchemaMismatch` if the stored fingerprint of a table differs. Databases
+    /// created before fingerprints were stored always pass.
+    fn check_schema_tx<K: TransactionKind>(
+        &self,
+        tx: &libmdbx_remote::TransactionAny<K>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async move {
+            let Some(stored) = stored_fingerprints_tx(tx).await? else {
+                return Ok(());
+            };
+            let mismatched = mismatched_tables(&stored, &Self::schema_fingerprints());
+            if !mismatched.is_empty() {
+                return Err(MDBXDeriveError::SchemaMismatch(mismatched).into());
+            }
+            Ok(())
+        }
+    }
+
+    fn check_schema(&self) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async move {
+            let tx = self.env().begin_ro_txn().await?;
+            self.check_schema_tx(&tx).await
+        }
+    }
+
+    fn stats_all(
+        &self,
+    ) -> impl Future<Output = Result<HashMap<String, TableStat>, Self::Error>> + Send {
+        async move {
+            let tx = self.env().begin_ro_txn().await?;
+            Self::Tables::stats_all(&tx).await
+        }
+    }
+
+    /// Runs `visitor` against every table owned by the database, e.g. with a transaction it
+    /// holds.
+    fn visit_tables<V: TableVisitor<Self::Error> + Send>(
+        &self,
+        visitor: &mut V,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        Self::Tables::visit_all(visitor)
+    }
+
+    fn clear_all(&self) -> impl Future<Output = Result<(), Self::Error>> + Send
+    where
+        Self: HasMDBXEnvironment<Mode = RW>,
+    {
+        async move {
+            let tx = WriteTxn::begin(self.env()).await?;
+            let result = Self::Tables::clear_all(&tx).await;
+            tx.finish(result).await?;
+            Ok(())
+        }
+    }
+
+    /// Drops every table of the database and returns the environment to open it again.
+    ///
+    /// # Safety
+    /// The same as `MDBXTable::drop_table_tx`, for every table, clones of the database and
+    /// its `*Dbi` struct included.
+    unsafe fn drop_all(self) -> impl Future<Output = Result<EnvironmentAny, Self::Error>> + Send
+    where
+        Self: HasMDBXEnvironment<Mode = RW>,
+    {
+        async move {
+            let tx = WriteTxn::begin(self.env()).await?;
+            let result = unsafe { Self::Tables::drop_all(&tx) }.await;
+            tx.finish(result).await?;
+            Ok(self.env().clone())
+        }
+    }
 
-pub trait HasMDBXDBIStore {
-    fn dbis(&self) -> &HashMap<String, u32>;
+    fn begin_ro(&self) -> impl Future<Output = Result<DbTxn<'_, RO, Self>, Self::Error>> + Send {
+        async move { Ok(DbTxn::new(self, self.env().begin_ro_txn().await?)) }
+    }
 
-    fn dbi<T: MDBXTable>(&self) -> Option<u32> {
-        self.dbis()
-            .get(&T::NAME.map(|v| v.to_string()).unwrap_or_default())
-            .copied()
+    fn begin_rw(&self) -> impl Future<Output = Result<DbTxn<'_, RW, Self>, Self::Error>> + Send
+    where
+        Self: HasMDBXEnvironment<Mode = RW>,
+    {
+        async move { Ok(DbTxn::new(self, self.env().begin_rw_txn().await?)) }
     }
-}
 
-pub trait HasMDBXTables {
-    type Error: From<libmdbx_remote::ClientError> + From<MDBXDeriveError> + Send + 'static;
-    type Tables: MDBXTables<Self::Error>;
-}
+    fn read<R, F>(&self, f: F) -> impl Future<Output = Result<R, Self::Error>> + Send
+    where
+        R: Send,
+        F: for<'t> FnOnce(
+                &'t libmdbx_remote::TransactionAny<libmdbx_remote::RO>,
+            ) -> BoxFuture<'t, Result<R, Self::Error>>
+            + Send,
+    {
+        async move {
+            let tx = self.env().begin_ro_txn().await?;
+            f(&tx).await
+        }
+    }
 
-pub trait MDBXDatabase: Sized + Send + Sync + HasMDBXEnvironment + HasMDBXTables {
-    type Metadata: TableObjectEncode + TableObjectDecode + Send + Sync;
-    const METADATA_NAME: &'static [u8] = b"metadata";
+    /// Commits if `f` succeeds, otherwise the transaction is dropped and thus aborted.
+    fn write<R, F>(&self, f: F) -> impl Future<Output = Result<R, Self::Error>> + Send
+    where
+        Self: HasMDBXEnvironment<Mode = RW>,
+        R: Send,
+        F: for<'t> FnOnce(
+                &'t libmdbx_remote::TransactionAny<RW>,
+            ) -> BoxFuture<'t, Result<R, Self::Error>>
+            + Send,
+    {
+        async move {
+            let tx = WriteTxn::begin(self.env()).await?;
+            let result = f(&tx).await;
+            tx.finish(result).await
+        }
+    }
 
-    fn create_all(
+    /// Like `write`, but runs `f` again in a new transaction when the transaction fails with an
+    /// error considered transient by `policy`.
+    fn write_with_retry<R, F>(
         &self,
-        flags: DatabaseFlags,
-    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        policy: RetryPolicy<Self::Error>,
+        mut f: F,
+    ) -> impl Future<Output = Result<R, Self::Error>> + Send
+    where
+        Self: HasMDBXEnvironment<Mode = RW>,
+        R: Send,
+        F: for<'t> FnMut(
+                &'t libmdbx_remote::TransactionAny<RW>,
+            ) -> BoxFuture<'t, Result<R, Self::Error>>
+            + Send,
+    {
         async move {
-            let tx = self.env().begin_rw_txn().await?;
-            Self::Tables::create_all(&tx, flags).await?;
-            Ok(())
+            let mut retries = 0;
+            loop {
+                match self.write(&mut f).await {
+                    Err(e) if retries < policy.retries && (policy.is_map_full)(&e) => {
+                        // The failed transaction is gone, so the map can be resized
+                        match grow_map(self.env()) {
+                            Ok(true) => retries += 1,
+                            Ok(false) => return Err(e),
+                            Err(grow) => {
+                                tracing::warn!("failed to grow the map: {grow}");
+                                return Err(e);
+                            }
+                        }
+                    }
+                    Err(e) if retries < policy.retries && (policy.is_transient)(&e) => {
+                        retries += 1;
+                        if let Some(sleep) = policy.sleep {
+                            sleep(policy.backoff).await;
+                        }
+                    }
+                    r => return r,
+                }
+            }
         }
     }
 
+    /// `dbi` is the handle of the database metadata table, which is created if `None`. Legacy
+    /// metadata in the main DB is left to `migrate_metadata`, the written one takes precedence.
     fn write_metadata_tx(
         &self,
         dbi: Option<u32>,
         tx: &libmdbx_remote::TransactionAny<RW>,
         meta: &Self::Metadata,
-    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send
+    where
+        Self: HasMDBXEnvironment<Mode = RW>,
+    {
         async move {
             let dbi = if let Some(dbi) = dbi {
                 dbi
             } else {
-                tx.open_db(None).await?.dbi()
+                tx.create_db(Some(DATABASE_METADATA_NAME), DatabaseFlags::empty())
+                    .await?
+                    .dbi()
             };
-            Ok(tx
-                .put(
-                    dbi,
-                    Self::METADATA_NAME,
-                    &meta.table_encode()?,
-                    WriteFlags::default(),
-                )
-                .await?)
+            tx.put(
+                dbi,
+                Self::METADATA_NAME,
+                &meta.table_encode()?,
+                WriteFlags::default(),
+            )
+            .await?;
+            Ok(())
         }
     }
 
     fn write_metadata(
         &self,
         meta: &Self::Metadata,
-    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send
+    where
+        Self: HasMDBXEnvironment<Mode = RW>,
+    {
         async move {
-            let tx = self.env().begin_rw_txn().await?;
-            self.write_metadata_tx(None, &tx, meta).await?;
-            tx.commit().await?;
+            let tx = WriteTxn::begin(self.env()).await?;
+            let result = self.write_metadata_tx(None, &tx, meta).await;
+            tx.finish(result).await?;
             Ok(())
         }
     }
 
+    /// Falls back to the legacy metadata in the main DB until it is migrated.
     fn metadata_tx<K: TransactionKind>(
         &self,
         dbi: Option<u32>,
         tx: &libmdbx_remote::TransactionAny<K>,
     ) -> impl Future<Output = Result<Option<Self::Metadata>, Self::Error>> + Send {
         async move {
-            let dbi = if let Some(dbi) = dbi {
-                dbi
-            } else {
-                tx.open_db(None).await?.dbi()
+            let dbi = match dbi {
+                Some(dbi) => Some(dbi),
+                None => open_reserved_table_tx(tx, DATABASE_METADATA_NAME).await?,
             };
-            Ok(tx
-                .get::<Vec<u8>>(dbi, Self::METADATA_NAME)
+            let v = match dbi {
+                Some(dbi) => tx.get::<Vec<u8>>(dbi, Self::METADATA_NAME).await?,
+                None => None,
+            };
+            let v = match v {
+                Some(v) => Some(v),
+                None => legacy_metadata_tx::<Self, _>(tx).await?.map(|(_, v)| v),
+            };
+            Ok(v.map(|v| Self::Metadata::table_decode(&v)).transpose()?)
+        }
+    }
+
+    /// Moves the legacy metadata from the main DB into the database metadata table, returns
+    /// whether there was any.
+    fn migrate_metadata_tx(
+        &self,
+        tx: &libmdbx_remote::TransactionAny<RW>,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send
+    where
+        Self: HasMDBXEnvironment<Mode = RW>,
+    {
+        async move {
+            let Some((main, v)) = legacy_metadata_tx::<Self, _>(tx).await? else {
+                return Ok(false);
+            };
+            let dbi = tx
+                .create_db(Some(DATABASE_METADATA_NAME), DatabaseFlags::empty())
                 .await?
-                .map(|v| Self::Metadata::table_decode(&v))
-                .transpose()?)
+                .dbi();
+            // Metadata written since takes precedence
+            if tx.get::<()>(dbi, Self::METADATA_NAME).await?.is_none() {
+                tx.put(dbi, Self::METADATA_NAME, &v, WriteFlags::default())
+                    .await?;
+            }
+            tx.del(main, Self::METADATA_NAME, None).await?;
+            Ok(true)
+        }
+    }
+
+    /// The version of the last applied migration, see `migration::run_migrations`.
+    fn schema_version(&self) -> impl Future<Output = Result<u64, Self::Error>> + Send {
+        async move {
+            let tx = self.env().begin_ro_txn().await?;
+            Ok(schema_version_tx(&tx).await?)
+        }
+    }
+
+    fn migrate_metadata(&self) -> impl Future<Output = Result<bool, Self::Error>> + Send
+    where
+        Self: HasMDBXEnvironment<Mode = RW>,
+    {
+        async move {
+            let tx = WriteTxn::begin(self.env()).await?;
+            let result = self.migrate_metadata_tx(&tx).await;
+            tx.finish(result).await
         }
     }
 
@@ -289,22 +1671,73 @@ pub trait MDBXDatabase: Sized + Send + Sync + HasMDBXEnvironment + HasMDBXTables
 
 // macros to generate table/database
 
+// Table options follow the types after a `;`, e.g. `; name = "users", flags = INTEGER_DUP`.
+// `name` fixes the on-disk name, which is the struct name by default. `flags` are names of
+// `DatabaseFlags` added to the flags of the table kind. INTEGER_KEY and REVERSE_KEY are
+// rejected, as are the DUP flags in `mdbx_table!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __mdbx_table_name {
+    ($struct_name:ident;) => {
+        stringify!($struct_name)
+    };
+    ($struct_name:ident; name = $name:literal $(, $($rest:tt)*)?) => {
+        $name
+    };
+    ($struct_name:ident; flags = $($flag:ident)|+ $(, $($rest:tt)*)?) => {
+        $crate::__mdbx_table_name!($struct_name; $($($rest)*)?)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __mdbx_table_flags {
+    ($base:expr;) => {
+        $base
+    };
+    ($base:expr; flags = $($flag:ident)|+ $(, $($rest:tt)*)?) => {
+        $crate::__mdbx_table_flags!(
+            $base $(.union(mdbx_derive::mdbx::DatabaseFlags::$flag))+;
+            $($($rest)*)?
+        )
+    };
+    ($base:expr; name = $name:literal $(, $($rest:tt)*)?) => {
+        $crate::__mdbx_table_flags!($base; $($($rest)*)?)
+    };
+}
+
 #[macro_export]
 macro_rules! mdbx_dupsort_table {
     (
         $struct_name:ident,
         $key_type:ty,
         $value_type:ty
+        $(; $($options:tt)*)?
     ) => {
-        $crate::mdbx_dupsort_table!($struct_name, $key_type, $value_type, mdbx_derive::Error, ());
+        $crate::mdbx_dupsort_table!(
+            $struct_name,
+            $key_type,
+            $value_type,
+            mdbx_derive::Error,
+            ()
+            $(; $($options)*)?
+        );
     };
     (
         $struct_name:ident,
         $key_type:ty,
         $value_type:ty,
         $error_type:ty
+        $(; $($options:tt)*)?
     ) => {
-        $crate::mdbx_dupsort_table!($struct_name, $key_type, $value_type, $error_type, ());
+        $crate::mdbx_dupsort_table!(
+            $struct_name,
+            $key_type,
+            $value_type,
+            $error_type,
+            ()
+            $(; $($options)*)?
+        );
     };
     (
         $struct_name:ident,
@@ -312,6 +1745,7 @@ macro_rules! mdbx_dupsort_table {
         $value_type:ty,
         $error_type:ty,
         $metadata_type:ty
+        $(; $($options:tt)*)?
     ) => {
         impl mdbx_derive::MDBXTable for $struct_name {
             type Key = $key_type;
@@ -320,8 +1754,25 @@ macro_rules! mdbx_dupsort_table {
             type Metadata = $metadata_type;
 
             const DUPSORT: bool = true;
-            const NAME: Option<&'static str> = Some(stringify!($struct_name));
+            const FLAGS: mdbx_derive::mdbx::DatabaseFlags = $crate::__mdbx_table_flags!(
+                mdbx_derive::mdbx::DatabaseFlags::DUP_SORT;
+                $($($options)*)?
+            );
+            const NAME: Option<&'static str> =
+                Some($crate::__mdbx_table_name!($struct_name; $($($options)*)?));
         }
+
+        impl mdbx_derive::MDBXDupSortTable for $struct_name {}
+
+        const _: () = assert!(
+            !<$struct_name as mdbx_derive::MDBXTable>::FLAGS
+                .intersects(mdbx_derive::schema::CUSTOM_KEY_ORDER),
+            concat!(
+                "table ",
+                stringify!($struct_name),
+                " can't use INTEGER_KEY or REVERSE_KEY, keys are compared bytewise"
+            )
+        );
     };
 }
 
@@ -331,6 +1782,7 @@ macro_rules! mdbx_dupsort_table_def {
         $struct_name:ident,
         $key_type:ty,
         $value_type:ty
+        $(; $($options:tt)*)?
     ) => {
         $crate::mdbx_dupsort_table_def!(
             $struct_name,
@@ -338,6 +1790,7 @@ macro_rules! mdbx_dupsort_table_def {
             $value_type,
             mdbx_derive::Error,
             ()
+            $(; $($options)*)?
         );
     };
     (
@@ -345,8 +1798,16 @@ macro_rules! mdbx_dupsort_table_def {
         $key_type:ty,
         $value_type:ty,
         $error_type:ty
+        $(; $($options:tt)*)?
     ) => {
-        $crate::mdbx_dupsort_table_def!($struct_name, $key_type, $value_type, $error_type, ());
+        $crate::mdbx_dupsort_table_def!(
+            $struct_name,
+            $key_type,
+            $value_type,
+            $error_type,
+            ()
+            $(; $($options)*)?
+        );
     };
     (
         $struct_name:ident,
@@ -354,10 +1815,72 @@ macro_rules! mdbx_dupsort_table_def {
         $value_type:ty,
         $error_type:ty,
         $metadata_type:ty
+        $(; $($options:tt)*)?
     ) => {
         #[derive(Clone, Debug, Copy, Default)]
         pub struct $struct_name;
 
+        $crate::mdbx_dupsort_table!(
+            $struct_name,
+            $key_type,
+            $value_type,
+            $error_type,
+            $metadata_type
+            $(; $($options)*)?
+        );
+    };
+}
+
+#[macro_export]
+macro_rules! mdbx_dupfixed_table {
+    (
+        $struct_name:ident,
+        $key_type:ty,
+        $value_type:ty
+        $(; $($options:tt)*)?
+    ) => {
+        $crate::mdbx_dupfixed_table!(
+            $struct_name,
+            $key_type,
+            $value_type,
+            mdbx_derive::Error,
+            ()
+            $(; $($options)*)?
+        );
+    };
+    (
+        $struct_name:ident,
+        $key_type:ty,
+        $value_type:ty,
+        $error_type:ty
+        $(; $($options:tt)*)?
+    ) => {
+        $crate::mdbx_dupfixed_table!(
+            $struct_name,
+            $key_type,
+            $value_type,
+            $error_type,
+            ()
+            $(; $($options)*)?
+        );
+    };
+    (
+        $struct_name:ident,
+        $key_type:ty,
+        $value_type:ty,
+        $error_type:ty,
+        $metadata_type:ty
+        $(; $($options:tt)*)?
+    ) => {
+        const _: () = assert!(
+            <$value_type as mdbx_derive::FixedSizeObject>::FIXED_SIZE > 0,
+            concat!(
+                "DUP_FIXED table ",
+                stringify!($struct_name),
+                " needs a non-empty value"
+            )
+        );
+
         impl mdbx_derive::MDBXTable for $struct_name {
             type Key = $key_type;
             type Value = $value_type;
@@ -365,8 +1888,83 @@ macro_rules! mdbx_dupsort_table_def {
             type Metadata = $metadata_type;
 
             const DUPSORT: bool = true;
-            const NAME: Option<&'static str> = Some(stringify!($struct_name));
+            const FLAGS: mdbx_derive::mdbx::DatabaseFlags = $crate::__mdbx_table_flags!(
+                mdbx_derive::mdbx::DatabaseFlags::DUP_SORT
+                    .union(mdbx_derive::mdbx::DatabaseFlags::DUP_FIXED);
+                $($($options)*)?
+            );
+            const NAME: Option<&'static str> =
+                Some($crate::__mdbx_table_name!($struct_name; $($($options)*)?));
         }
+
+        impl mdbx_derive::MDBXDupSortTable for $struct_name {}
+
+        impl mdbx_derive::MDBXDupFixedTable for $struct_name {}
+
+        const _: () = assert!(
+            !<$struct_name as mdbx_derive::MDBXTable>::FLAGS
+                .intersects(mdbx_derive::schema::CUSTOM_KEY_ORDER),
+            concat!(
+                "table ",
+                stringify!($struct_name),
+                " can't use INTEGER_KEY or REVERSE_KEY, keys are compared bytewise"
+            )
+        );
+    };
+}
+
+#[macro_export]
+macro_rules! mdbx_dupfixed_table_def {
+    (
+        $struct_name:ident,
+        $key_type:ty,
+        $value_type:ty
+        $(; $($options:tt)*)?
+    ) => {
+        $crate::mdbx_dupfixed_table_def!(
+            $struct_name,
+            $key_type,
+            $value_type,
+            mdbx_derive::Error,
+            ()
+            $(; $($options)*)?
+        );
+    };
+    (
+        $struct_name:ident,
+        $key_type:ty,
+        $value_type:ty,
+        $error_type:ty
+        $(; $($options:tt)*)?
+    ) => {
+        $crate::mdbx_dupfixed_table_def!(
+            $struct_name,
+            $key_type,
+            $value_type,
+            $error_type,
+            ()
+            $(; $($options)*)?
+        );
+    };
+    (
+        $struct_name:ident,
+        $key_type:ty,
+        $value_type:ty,
+        $error_type:ty,
+        $metadata_type:ty
+        $(; $($options:tt)*)?
+    ) => {
+        #[derive(Clone, Debug, Copy, Default)]
+        pub struct $struct_name;
+
+        $crate::mdbx_dupfixed_table!(
+            $struct_name,
+            $key_type,
+            $value_type,
+            $error_type,
+            $metadata_type
+            $(; $($options)*)?
+        );
     };
 }
 
@@ -376,16 +1974,32 @@ macro_rules! mdbx_table {
         $struct_name:ident,
         $key_type:ty,
         $value_type:ty
+        $(; $($options:tt)*)?
     ) => {
-        $crate::mdbx_table!($struct_name, $key_type, $value_type, mdbx_derive::Error, ());
+        $crate::mdbx_table!(
+            $struct_name,
+            $key_type,
+            $value_type,
+            mdbx_derive::Error,
+            ()
+            $(; $($options)*)?
+        );
     };
     (
         $struct_name:ident,
         $key_type:ty,
         $value_type:ty,
         $error_type:ty
+        $(; $($options:tt)*)?
     ) => {
-        $crate::mdbx_table!($struct_name, $key_type, $value_type, $error_type, ());
+        $crate::mdbx_table!(
+            $struct_name,
+            $key_type,
+            $value_type,
+            $error_type,
+            ()
+            $(; $($options)*)?
+        );
     };
     (
         $struct_name:ident,
@@ -393,6 +2007,7 @@ macro_rules! mdbx_table {
         $value_type:ty,
         $error_type:ty,
         $metadata_type:ty
+        $(; $($options:tt)*)?
     ) => {
         impl mdbx_derive::MDBXTable for $struct_name {
             type Key = $key_type;
@@ -401,8 +2016,33 @@ macro_rules! mdbx_table {
             type Metadata = $metadata_type;
 
             const DUPSORT: bool = false;
-            const NAME: Option<&'static str> = Some(stringify!($struct_name));
+            const FLAGS: mdbx_derive::mdbx::DatabaseFlags = $crate::__mdbx_table_flags!(
+                mdbx_derive::mdbx::DatabaseFlags::empty();
+                $($($options)*)?
+            );
+            const NAME: Option<&'static str> =
+                Some($crate::__mdbx_table_name!($struct_name; $($($options)*)?));
         }
+
+        const _: () = assert!(
+            !<$struct_name as mdbx_derive::MDBXTable>::FLAGS
+                .intersects(mdbx_derive::schema::CUSTOM_KEY_ORDER),
+            concat!(
+                "table ",
+                stringify!($struct_name),
+                " can't use INTEGER_KEY or REVERSE_KEY, keys are compared bytewise"
+            )
+        );
+
+        const _: () = assert!(
+            !<$struct_name as mdbx_derive::MDBXTable>::FLAGS
+                .intersects(mdbx_derive::schema::DUP_FLAGS),
+            concat!(
+                "table ",
+                stringify!($struct_name),
+                " can't use DUP flags, use mdbx_dupsort_table! or mdbx_dupfixed_table!"
+            )
+        );
     };
 }
 
@@ -412,16 +2052,32 @@ macro_rules! mdbx_table_def {
         $struct_name:ident,
         $key_type:ty,
         $value_type:ty
+        $(; $($options:tt)*)?
     ) => {
-        $crate::mdbx_table_def!($struct_name, $key_type, $value_type, mdbx_derive::Error, ());
+        $crate::mdbx_table_def!(
+            $struct_name,
+            $key_type,
+            $value_type,
+            mdbx_derive::Error,
+            ()
+            $(; $($options)*)?
+        );
     };
     (
         $struct_name:ident,
         $key_type:ty,
         $value_type:ty,
         $error_type:ty
+        $(; $($options:tt)*)?
     ) => {
-        $crate::mdbx_table_def!($struct_name, $key_type, $value_type, $error_type, ());
+        $crate::mdbx_table_def!(
+            $struct_name,
+            $key_type,
+            $value_type,
+            $error_type,
+            ()
+            $(; $($options)*)?
+        );
     };
     (
         $struct_name:ident,
@@ -429,84 +2085,181 @@ macro_rules! mdbx_table_def {
         $value_type:ty,
         $error_type:ty,
         $metadata_type:ty
+        $(; $($options:tt)*)?
     ) => {
         #[derive(Clone, Debug, Copy, Default)]
         pub struct $struct_name;
 
-        impl mdbx_derive::MDBXTable for $struct_name {
-            type Key = $key_type;
-            type Value = $value_type;
-            type Error = $error_type;
-            type Metadata = $metadata_type;
-
-            const DUPSORT: bool = false;
-            const NAME: Option<&'static str> = Some(stringify!($struct_name));
-        }
+        $crate::mdbx_table!(
+            $struct_name,
+            $key_type,
+            $value_type,
+            $error_type,
+            $metadata_type
+            $(; $($options)*)?
+        );
     };
 }
 
+// Tables are type paths, or `field = path` to name the DBI field of a table, which is the
+// snake case of the last path segment otherwise.
 #[macro_export]
 macro_rules! mdbx_database {
     (
         $db_name:ident,
         $error_type:ty,
         $metadata_type:ty,
-        $($tables:ty),+
+        $($tables:tt)+
     ) => {
-        mdbx_derive::paste::paste! {
-            mdbx_derive::generate_dbi_struct!([<$db_name Dbi>], $error_type, $($tables),*);
+        mdbx_derive::generate_database!($db_name, $error_type, $metadata_type, $($tables)+);
+    };
+}
 
-            #[derive(Debug, Clone)]
-            pub struct $db_name {
-                pub env: mdbx_derive::mdbx::EnvironmentAny,
-                pub dbis: [<$db_name Dbi>]
-            }
+// The database struct around a DBI struct, shared by `mdbx_database!` and
+// `#[derive(MDBXDatabase)]`. The tables of the database are those owned by the DBI struct.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __mdbx_database_struct {
+    (
+        $(#[$attr:meta])*
+        $vis:vis $db_name:ident,
+        $dbi_name:ident,
+        $error_type:ty,
+        $metadata_type:ty
+        $(; migrations = $migrations:expr)?
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        $vis struct $db_name<M: mdbx_derive::DatabaseEnv = mdbx_derive::mdbx::RW> {
+            /// A `ReadOnlyEnvironment` for read-only handles
+            pub env: M::Env,
+            pub dbis: $dbi_name<M>,
+            dbi_cache: mdbx_derive::DbiCache
+        }
 
-            impl std::ops::Deref for $db_name {
-                type Target = mdbx_derive::mdbx::EnvironmentAny;
-                fn deref(&self) -> &Self::Target {
-                    &self.env
+        impl<M: mdbx_derive::DatabaseEnv> Clone for $db_name<M> {
+            fn clone(&self) -> Self {
+                Self {
+                    env: self.env.clone(),
+                    dbis: self.dbis,
+                    dbi_cache: self.dbi_cache.clone()
                 }
             }
+        }
 
-            impl $db_name {
-                pub fn new(env: mdbx_derive::mdbx::EnvironmentAny, dbis: [<$db_name Dbi>]) -> Self {
-                    Self {
-                        env,
-                        dbis
-                    }
-                }
+        impl std::ops::Deref for $db_name<mdbx_derive::mdbx::RW> {
+            type Target = mdbx_derive::mdbx::EnvironmentAny;
+            fn deref(&self) -> &Self::Target {
+                &self.env
+            }
+        }
 
-                pub async fn open_create_tables_with_defaults(url: &str, defaults: mdbx_derive::mdbx::EnvironmentBuilder) -> Result<Self, $error_type> {
-                    let env =  mdbx_derive::mdbx::EnvironmentAny::open_with_defaults(url, defaults).await?;
-                    let dbis = [<$db_name Dbi>]::new(&env)
-                            .await?;
-                    Ok(Self::new(env, dbis))
+        impl<M: mdbx_derive::DatabaseEnv> $db_name<M> {
+            pub fn new(env: mdbx_derive::mdbx::EnvironmentAny, dbis: $dbi_name<M>) -> Self {
+                let dbi_cache = mdbx_derive::DbiCache::for_env(&env);
+                dbis.fill_dbi_cache(&dbi_cache);
+                Self {
+                    env: M::wrap_env(env),
+                    dbis,
+                    dbi_cache
                 }
+            }
+        }
 
-                pub async fn open_tables_with_defaults(url: &str, defaults: mdbx_derive::mdbx::EnvironmentBuilder) -> Result<Self, $error_type> {
-                    let env =  mdbx_derive::mdbx::EnvironmentAny::open_with_defaults(url, defaults).await?;
-                    let tx = env.begin_ro_txn().await?;
-                    let dbis = [<$db_name Dbi>]::new_ro(&tx)
-                            .await?;
-                    Ok(Self::new(env, dbis))
+        impl $db_name<mdbx_derive::mdbx::RW> {
+            pub async fn open_create_tables_with_defaults(url: &str, defaults: mdbx_derive::mdbx::EnvironmentBuilder) -> Result<Self, $error_type> {
+                let env =  mdbx_derive::mdbx::EnvironmentAny::open_with_defaults(url, defaults).await?;
+                let dbis = $dbi_name::new(&env)
+                        .await?;
+                let db = Self::new(env, dbis);
+                mdbx_derive::MDBXDatabase::migrate_metadata(&db).await?;
+                #[allow(unused_mut)]
+                let mut migrated = false;
+                $(
+                    let version = mdbx_derive::MDBXDatabase::schema_version(&db).await?;
+                    migrated = db.migrate($migrations).await? != version;
+                )?
+                // Tables that existed must still match their fingerprint, unless a migration
+                // just converted them
+                if !migrated {
+                    mdbx_derive::MDBXDatabase::check_schema(&db).await?;
                 }
+                mdbx_derive::MDBXDatabase::write_schema_fingerprints(&db).await?;
+                Ok(db)
+            }
+
+            pub async fn migrate(&self, migrations: &[mdbx_derive::Migration<$dbi_name, $error_type>]) -> Result<u64, $error_type> {
+                mdbx_derive::migration::run_migrations(&self.env, &self.dbis, migrations).await
+            }
+
+            pub async fn open_tables_with_defaults(url: &str, defaults: mdbx_derive::mdbx::EnvironmentBuilder) -> Result<Self, $error_type> {
+                let db = Self::open_tables_with_defaults_unchecked(url, defaults).await?;
+                mdbx_derive::MDBXDatabase::check_schema(&db).await?;
+                Ok(db)
+            }
+
+            /// Skips the schema fingerprint check
+            pub async fn open_tables_with_defaults_unchecked(url: &str, defaults: mdbx_derive::mdbx::EnvironmentBuilder) -> Result<Self, $error_type> {
+                let env =  mdbx_derive::mdbx::EnvironmentAny::open_with_defaults(url, defaults).await?;
+                let tx = env.begin_ro_txn().await?;
+                let dbis = $dbi_name::new_ro(&tx)
+                        .await?;
+                Ok(Self::new(env, dbis))
+            }
+        }
+
+        /// The write methods of `MDBXDatabase` and the write helpers of the DBI struct are not
+        /// available on read-only handles.
+        impl $db_name<mdbx_derive::mdbx::RO> {
+            pub async fn open_tables_read_only_with_defaults(url: &str, defaults: mdbx_derive::mdbx::EnvironmentBuilder) -> Result<Self, $error_type> {
+                let db = Self::open_tables_read_only_with_defaults_unchecked(url, defaults).await?;
+                mdbx_derive::MDBXDatabase::check_schema(&db).await?;
+                Ok(db)
+            }
+
+            /// Skips the schema fingerprint check. The environment is opened read-only.
+            pub async fn open_tables_read_only_with_defaults_unchecked(url: &str, defaults: mdbx_derive::mdbx::EnvironmentBuilder) -> Result<Self, $error_type> {
+                let env = mdbx_derive::open_env_read_only(url, defaults).await?;
+                let tx = env.begin_ro_txn().await?;
+                let dbis = $dbi_name::new_read_only(&tx)
+                        .await?;
+                Ok(Self::new(env, dbis))
             }
         }
 
-        impl mdbx_derive::HasMDBXEnvironment for $db_name {
+        impl<M: mdbx_derive::DatabaseEnv> mdbx_derive::HasMDBXEnvironment for $db_name<M> {
+            type Mode = M;
+
             fn env(&self) -> &mdbx_derive::mdbx::EnvironmentAny {
-                &self.env
+                M::env_any(&self.env)
+            }
+
+            fn dbi_cache(&self) -> Option<&mdbx_derive::DbiCache> {
+                Some(&self.dbi_cache)
             }
         }
 
-        impl mdbx_derive::MDBXDatabase for $db_name {
+        impl<M: mdbx_derive::DatabaseEnv> mdbx_derive::MDBXDatabase for $db_name<M> {
             type Metadata = $metadata_type;
         }
 
-        impl mdbx_derive::HasMDBXTables for $db_name {
+        impl<M: mdbx_derive::DatabaseEnv> mdbx_derive::HasMDBXTableDbis for $db_name<M> {
+            type Mode = M;
+            type DbiTables = <$dbi_name<M> as mdbx_derive::HasMDBXTableDbis>::DbiTables;
+            type OptionalDbiTables = <$dbi_name<M> as mdbx_derive::HasMDBXTableDbis>::OptionalDbiTables;
+
+            fn table_dbi(&self, index: usize) -> u32 {
+                mdbx_derive::HasMDBXTableDbis::table_dbi(&self.dbis, index)
+            }
+
+            fn optional_table_dbi(&self, index: usize) -> Option<u32> {
+                mdbx_derive::HasMDBXTableDbis::optional_table_dbi(&self.dbis, index)
+            }
+        }
+
+        impl<M: mdbx_derive::DatabaseEnv> mdbx_derive::HasMDBXTables for $db_name<M> {
             type Error = $error_type;
-            type Tables = mdbx_derive::tuple_list_type!($($tables),*);
+            type Tables = <$dbi_name<M> as mdbx_derive::HasMDBXTables>::Tables;
         }
     };
 }
@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+
+use libmdbx_remote::{
+    ClientError, DatabaseFlags, RW, TransactionAny, TransactionKind, WriteFlags, ffi,
+};
+
+use crate::{
+    error::MDBXDeriveError,
+    key::KeyObjectDecode,
+    orm::{DATABASE_METADATA_NAME, MDBXTable, open_reserved_table_tx},
+    table::TableObjectEncode,
+};
+
+/// The flags stored with a table, the others only apply when opening it.
+pub const PERSISTENT_FLAGS: DatabaseFlags = DatabaseFlags::REVERSE_KEY
+    .union(DatabaseFlags::DUP_SORT)
+    .union(DatabaseFlags::INTEGER_KEY)
+    .union(DatabaseFlags::DUP_FIXED)
+    .union(DatabaseFlags::INTEGER_DUP)
+    .union(DatabaseFlags::REVERSE_DUP);
+
+/// Key orders other than bytewise, which range scans and batch appends rely on.
+pub const CUSTOM_KEY_ORDER: DatabaseFlags =
+    DatabaseFlags::REVERSE_KEY.union(DatabaseFlags::INTEGER_KEY);
+
+/// Duplicate orders other than bytewise.
+pub const CUSTOM_DUP_ORDER: DatabaseFlags =
+    DatabaseFlags::REVERSE_DUP.union(DatabaseFlags::INTEGER_DUP);
+
+/// The flags that only make sense for tables with duplicates.
+pub const DUP_FLAGS: DatabaseFlags = DatabaseFlags::DUP_SORT
+    .union(DatabaseFlags::DUP_FIXED)
+    .union(CUSTOM_DUP_ORDER);
+
+/// The key of the table fingerprints in the database metadata table.
+pub const SCHEMA_FINGERPRINT_KEY: &[u8] = b"schema_fingerprint";
+
+/// Table fingerprints keyed by table name.
+pub type Fingerprints = BTreeMap<String, u64>;
+
+/// 64-bit FNV-1a, which unlike `DefaultHasher` is stable across builds.
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv64(u64);
+
+impl Default for Fnv64 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Fnv64 {
+    pub const fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    /// Length prefixed, so that consecutive fields can't run into each other.
+    pub fn write_field(&mut self, bytes: &[u8]) {
+        self.write(&(bytes.len() as u64).to_be_bytes());
+        self.write(bytes);
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Hashes the name, flags, key size and value codec of a table.
+pub fn table_fingerprint<T: MDBXTable>() -> u64 {
+    let mut h = Fnv64::new();
+    h.write_field(T::NAME.unwrap_or_default().as_bytes());
+    h.write_field(&T::FLAGS.bits().to_be_bytes());
+    h.write_field(&(T::Key::KEYSIZE as u64).to_be_bytes());
+    h.write_field(T::Value::codec_id().as_bytes());
+    h.finish()
+}
+
+pub fn combined_fingerprint(fingerprints: &Fingerprints) -> u64 {
+    let mut h = Fnv64::new();
+    for (name, fingerprint) in fingerprints {
+        h.write_field(name.as_bytes());
+        h.write(&fingerprint.to_be_bytes());
+    }
+    h.finish()
+}
+
+pub async fn stored_fingerprints_tx<K: TransactionKind>(
+    tx: &TransactionAny<K>,
+) -> Result<Option<Fingerprints>, MDBXDeriveError> {
+    let Some(dbi) = open_reserved_table_tx(tx, DATABASE_METADATA_NAME).await? else {
+        return Ok(None);
+    };
+    let Some(v) = tx.get::<Vec<u8>>(dbi, SCHEMA_FINGERPRINT_KEY).await? else {
+        return Ok(None);
+    };
+    Ok(Some(postcard::from_bytes(&v)?))
+}
+
+/// Merges `fingerprints` into the stored ones, so that databases sharing an environment keep
+/// the fingerprints of each other's tables.
+pub async fn write_fingerprints_tx(
+    tx: &TransactionAny<RW>,
+    fingerprints: &Fingerprints,
+) -> Result<(), MDBXDeriveError> {
+    let mut stored = stored_fingerprints_tx(tx).await?.unwrap_or_default();
+    stored.extend(fingerprints.iter().map(|(k, v)| (k.clone(), *v)));
+    let dbi = tx
+        .create_db(Some(DATABASE_METADATA_NAME), DatabaseFlags::empty())
+        .await?
+        .dbi();
+    tx.put(
+        dbi,
+        SCHEMA_FINGERPRINT_KEY,
+        &postcard::to_allocvec(&stored)?,
+        WriteFlags::default(),
+    )
+    .await?;
+    Ok(())
+}
+
+/// The tables whose stored fingerprint differs, tables without one are skipped.
+pub fn mismatched_tables(stored: &Fingerprints, current: &Fingerprints) -> Vec<String> {
+    current
+        .iter()
+        .filter(|(name, fingerprint)| stored.get(*name).is_some_and(|s| s != *fingerprint))
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Reads the flags of the table opened as `dbi`. Remote environments don't expose them and
+/// give `None`.
+pub fn dbi_flags_tx<K: TransactionKind>(
+    tx: &TransactionAny<K>,
+    dbi: u32,
+) -> Result<Option<DatabaseFlags>, MDBXDeriveError> {
+    let TransactionAny::Local(tx) = tx else {
+        return Ok(None);
+    };
+    let mut flags = 0;
+    let mut state = 0;
+    // SAFETY: the transaction pointer is only used under its lock
+    let rc = tx
+        .txn_execute(|txn| unsafe { ffi::mdbx_dbi_flags_ex(txn, dbi, &mut flags, &mut state) })
+        .map_err(ClientError::MDBX)?;
+    if rc != 0 {
+        return Err(ClientError::MDBX(libmdbx_remote::Error::from_err_code(rc)).into());
+    }
+    Ok(Some(DatabaseFlags::from_bits_truncate(flags)))
+}
+
+/// Whether the main DB record of `name` is a table rather than a plain value, `None` for remote
+/// environments, which can't list their tables.
+pub fn is_table_tx<K: TransactionKind>(
+    tx: &TransactionAny<K>,
+    name: &[u8],
+) -> Result<Option<bool>, MDBXDeriveError> {
+    unsafe extern "C" fn matches(
+        ctx: *mut std::ffi::c_void,
+        _txn: *const ffi::MDBX_txn,
+        name: *const ffi::MDBX_val,
+        _flags: ffi::MDBX_db_flags_t,
+        _stat: *const ffi::MDBX_stat,
+        _dbi: ffi::MDBX_dbi,
+    ) -> std::ffi::c_int {
+        // SAFETY: `ctx` is the name being looked up, `name` is valid during the call
+        let (wanted, name) = unsafe {
+            let name = &*name;
+            (
+                &*(ctx as *const &[u8]),
+                std::slice::from_raw_parts(name.iov_base as *const u8, name.iov_len),
+            )
+        };
+        if *wanted == name {
+            ffi::MDBX_RESULT_TRUE
+        } else {
+            ffi::MDBX_SUCCESS
+        }
+    }
+
+    let TransactionAny::Local(tx) = tx else {
+        return Ok(None);
+    };
+    let mut ctx = name;
+    // SAFETY: the transaction pointer is only used under its lock and `ctx` outlives the call
+    let rc = tx
+        .txn_execute(|txn| unsafe {
+            ffi::mdbx_enumerate_tables(txn, Some(matches), &mut ctx as *mut &[u8] as *mut _)
+        })
+        .map_err(ClientError::MDBX)?;
+    match rc {
+        ffi::MDBX_SUCCESS => Ok(Some(false)),
+        ffi::MDBX_RESULT_TRUE => Ok(Some(true)),
+        rc => Err(ClientError::MDBX(libmdbx_remote::Error::from_err_code(rc)).into()),
+    }
+}
+
+/// Fails with `DuplicateTable` if tables of a database share a name, which would make them the
+/// same table on disk and collide in fingerprints, stats and the `DbiCache`.
+pub fn check_unique_names(names: &[Option<&'static str>]) -> Result<(), MDBXDeriveError> {
+    for (i, name) in names.iter().enumerate() {
+        if names[..i].contains(name) {
+            return Err(MDBXDeriveError::DuplicateTable(name.unwrap_or_default()));
+        }
+    }
+    Ok(())
+}
+
+pub async fn table_exists_tx<K: TransactionKind>(
+    tx: &TransactionAny<K>,
+    name: Option<&str>,
+) -> Result<bool, MDBXDeriveError> {
+    let Some(name) = name else {
+        return Ok(true);
+    };
+    let main = tx.open_db(None).await?.dbi();
+    Ok(tx.get::<()>(main, name.as_bytes()).await?.is_some())
+}
+
+/// Fails with `FlagsMismatch` if the table opened as `dbi` was created with other flags than
+/// `expected`. Tables of remote environments are not checked, see `dbi_flags_tx`.
+pub fn check_flags_tx<K: TransactionKind>(
+    tx: &TransactionAny<K>,
+    name: Option<&str>,
+    dbi: u32,
+    expected: DatabaseFlags,
+) -> Result<(), MDBXDeriveError> {
+    let Some(actual) = dbi_flags_tx(tx, dbi)? else {
+        return Ok(());
+    };
+    let expected = expected.intersection(PERSISTENT_FLAGS);
+    let actual = actual.intersection(PERSISTENT_FLAGS);
+    if expected.bits() != actual.bits() {
+        return Err(MDBXDeriveError::FlagsMismatch {
+            table: name.unwrap_or_default().to_string(),
+            expected: expected.bits(),
+            actual: actual.bits(),
+        });
+    }
+    Ok(())
+}
+
+pub fn check_table_flags_tx<T: MDBXTable, K: TransactionKind>(
+    tx: &TransactionAny<K>,
+    dbi: u32,
+) -> Result<(), MDBXDeriveError> {
+    check_flags_tx(tx, T::NAME, dbi, T::FLAGS)
+}
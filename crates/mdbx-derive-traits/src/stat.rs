@@ -0,0 +1,36 @@
+use libmdbx_remote::Stat;
+
+/// Statistics of a single table, from `mdbx_dbi_stat`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TableStat {
+    pub entries: usize,
+    pub depth: u32,
+    pub branch_pages: usize,
+    pub leaf_pages: usize,
+    pub overflow_pages: usize,
+    pub page_size: u32,
+}
+
+impl TableStat {
+    pub fn pages(&self) -> usize {
+        self.branch_pages + self.leaf_pages + self.overflow_pages
+    }
+
+    /// The space taken by the pages of the table.
+    pub fn bytes(&self) -> u64 {
+        self.pages() as u64 * self.page_size as u64
+    }
+}
+
+impl From<Stat> for TableStat {
+    fn from(stat: Stat) -> Self {
+        Self {
+            entries: stat.entries(),
+            depth: stat.depth(),
+            branch_pages: stat.branch_pages(),
+            leaf_pages: stat.leaf_pages(),
+            overflow_pages: stat.overflow_pages(),
+            page_size: stat.page_size(),
+        }
+    }
+}
@@ -4,16 +4,36 @@ use crate::error::MDBXDeriveError;
 
 pub trait TableObjectEncode {
     fn table_encode(&self) -> Result<Vec<u8>, MDBXDeriveError>;
+
+    /// Identifies the encoding in schema fingerprints, so it must stay the same across builds
+    /// and renames. The codec wrappers and derives use fixed ids, the same for byte-compatible
+    /// encodings, e.g. `Zstd<Postcard<T>>` and `ZstdPostcardObject`. Other types have none
+    /// unless they pick one.
+    fn codec_id() -> Cow<'static, str>
+    where
+        Self: Sized,
+    {
+        Cow::Borrowed("")
+    }
 }
 
 pub trait TableObjectDecode: Sized {
     fn table_decode(val: &[u8]) -> Result<Self, MDBXDeriveError>;
 }
 
+/// Objects always encoded to `FIXED_SIZE` bytes, required by DUP_FIXED tables.
+pub trait FixedSizeObject: TableObjectDecode {
+    const FIXED_SIZE: usize;
+}
+
 impl TableObjectEncode for Vec<u8> {
     fn table_encode(&self) -> Result<Vec<u8>, MDBXDeriveError> {
         Ok(self.clone())
     }
+
+    fn codec_id() -> Cow<'static, str> {
+        Cow::Borrowed("Bytes")
+    }
 }
 
 impl TableObjectDecode for Vec<u8> {
@@ -26,6 +46,10 @@ impl TableObjectEncode for Cow<'_, [u8]> {
     fn table_encode(&self) -> Result<Vec<u8>, MDBXDeriveError> {
         Ok(self.clone().into_owned())
     }
+
+    fn codec_id() -> Cow<'static, str> {
+        Cow::Borrowed("Bytes")
+    }
 }
 
 impl TableObjectDecode for Cow<'_, [u8]> {
@@ -48,4 +72,8 @@ impl TableObjectEncode for () {
     fn table_encode(&self) -> Result<Vec<u8>, MDBXDeriveError> {
         Ok(vec![])
     }
+
+    fn codec_id() -> Cow<'static, str> {
+        Cow::Borrowed("Empty")
+    }
 }
@@ -0,0 +1,198 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use libmdbx_remote::{CommitLatency, EnvironmentAny, RW, TransactionAny, TransactionKind};
+
+use crate::{
+    error::MDBXDeriveError,
+    handle::TxnTable,
+    orm::{HasMDBXTableDbis, MDBXTable, OptionalTableMode, TableIndex, TableMode},
+};
+
+/// Commits `tx`, a transaction that MDBX aborted instead of committing is an error as well.
+pub async fn commit_tx(tx: TransactionAny<RW>) -> Result<CommitLatency, MDBXDeriveError> {
+    match tx.commit().await {
+        Ok((false, latency)) => Ok(latency),
+        Ok((true, _)) => Err(MDBXDeriveError::CommitAborted),
+        Err(e) => Err(MDBXDeriveError::Commit(e)),
+    }
+}
+
+/// A write transaction that has to be committed or aborted explicitly. Dropping it otherwise
+/// aborts all writes as well, but logs a warning, as that is usually a forgotten `commit`.
+#[must_use = "dropping a WriteTxn aborts it, call `commit` to keep the writes"]
+#[derive(Debug)]
+pub struct WriteTxn {
+    // `None` once committed or aborted
+    tx: Option<TransactionAny<RW>>,
+}
+
+impl WriteTxn {
+    pub async fn begin(env: &EnvironmentAny) -> Result<Self, MDBXDeriveError> {
+        Ok(Self::new(env.begin_rw_txn().await?))
+    }
+
+    pub fn new(tx: TransactionAny<RW>) -> Self {
+        Self { tx: Some(tx) }
+    }
+
+    pub async fn commit(mut self) -> Result<CommitLatency, MDBXDeriveError> {
+        let tx = self.tx.take().expect("WriteTxn already finished");
+        commit_tx(tx).await
+    }
+
+    pub fn abort(mut self) {
+        self.tx.take();
+    }
+
+    /// Commits if `result` is `Ok`, otherwise aborts, e.g.
+    /// `let r = async { .. }.await; tx.finish(r).await`.
+    pub async fn finish<R, E: From<MDBXDeriveError>>(self, result: Result<R, E>) -> Result<R, E> {
+        match result {
+            Ok(r) => {
+                self.commit().await?;
+                Ok(r)
+            }
+            Err(e) => {
+                self.abort();
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Deref for WriteTxn {
+    type Target = TransactionAny<RW>;
+    fn deref(&self) -> &Self::Target {
+        self.tx.as_ref().expect("WriteTxn already finished")
+    }
+}
+
+impl Drop for WriteTxn {
+    fn drop(&mut self) {
+        if self.tx.is_some() && !std::thread::panicking() {
+            tracing::warn!("WriteTxn dropped without commit or abort, its writes are discarded");
+        }
+    }
+}
+
+/// A transaction of the database `D`, returned by `MDBXDatabase::begin_ro` and `begin_rw`. The
+/// tables are reached through the DBIs of `D`, so they can't be mixed up with the DBIs of another
+/// environment. Derefs to the raw transaction, dropping it aborts.
+#[must_use = "dropping a DbTxn aborts it, call `commit` to keep the writes"]
+pub struct DbTxn<'db, K: TransactionKind, D> {
+    db: &'db D,
+    tx: TransactionAny<K>,
+}
+
+impl<K: TransactionKind, D> fmt::Debug for DbTxn<'_, K, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DbTxn")
+            .field("db", &std::any::type_name::<D>())
+            .field("tx", &self.tx)
+            .finish()
+    }
+}
+
+impl<'db, K: TransactionKind, D> DbTxn<'db, K, D> {
+    /// `tx` must be a transaction of the environment of `db`.
+    pub(crate) fn new(db: &'db D, tx: TransactionAny<K>) -> Self {
+        Self { db, tx }
+    }
+
+    pub fn db(&self) -> &'db D {
+        self.db
+    }
+
+    pub fn tx(&self) -> &TransactionAny<K> {
+        &self.tx
+    }
+
+    pub fn into_inner(self) -> TransactionAny<K> {
+        self.tx
+    }
+
+    /// Read-only tables are read-only in a `RW` transaction too.
+    pub fn table<T: MDBXTable, I>(&self) -> TxnTable<'_, T, K, TableMode<D, T, I>>
+    where
+        D: HasMDBXTableDbis,
+        D::DbiTables: TableIndex<T, I>,
+    {
+        TxnTable::new(&self.tx, self.db.table::<T, I>())
+    }
+
+    /// `None` if the table didn't exist when the database opened.
+    pub fn optional_table<T: MDBXTable, I>(
+        &self,
+    ) -> Option<TxnTable<'_, T, K, OptionalTableMode<D, T, I>>>
+    where
+        D: HasMDBXTableDbis,
+        D::OptionalDbiTables: TableIndex<T, I>,
+    {
+        self.db
+            .optional_table::<T, I>()
+            .map(|handle| TxnTable::new(&self.tx, handle))
+    }
+}
+
+impl<'db, D> DbTxn<'db, RW, D> {
+    pub async fn commit(self) -> Result<CommitLatency, MDBXDeriveError> {
+        commit_tx(self.tx).await
+    }
+
+    pub fn abort(self) {}
+
+    /// Begins a nested transaction, which fails for environments opened with `WriteMap`.
+    pub async fn savepoint(&mut self) -> Result<Savepoint<'_, 'db, D>, MDBXDeriveError> {
+        let tx = self.tx.begin_nested_txn().await?;
+        Ok(Savepoint {
+            txn: DbTxn::new(self.db, tx),
+            _parent: PhantomData,
+        })
+    }
+}
+
+impl<K: TransactionKind, D> Deref for DbTxn<'_, K, D> {
+    type Target = TransactionAny<K>;
+    fn deref(&self) -> &Self::Target {
+        &self.tx
+    }
+}
+
+/// A nested transaction of a `DbTxn`. Its writes become part of the parent on `commit`, and are
+/// rolled back by `rollback` or dropping it. The parent is borrowed until then, as MDBX doesn't
+/// allow using it while the child is open. Derefs to a `DbTxn` for the table accessors and
+/// further savepoints.
+#[must_use = "dropping a Savepoint rolls it back, call `commit` to keep the writes"]
+pub struct Savepoint<'p, 'db, D> {
+    txn: DbTxn<'db, RW, D>,
+    _parent: PhantomData<&'p mut DbTxn<'db, RW, D>>,
+}
+
+impl<D> fmt::Debug for Savepoint<'_, '_, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Savepoint").field("txn", &self.txn).finish()
+    }
+}
+
+impl<D> Savepoint<'_, '_, D> {
+    pub async fn commit(self) -> Result<CommitLatency, MDBXDeriveError> {
+        self.txn.commit().await
+    }
+
+    pub fn rollback(self) {}
+}
+
+impl<'db, D> Deref for Savepoint<'_, 'db, D> {
+    type Target = DbTxn<'db, RW, D>;
+    fn deref(&self) -> &Self::Target {
+        &self.txn
+    }
+}
+
+impl<D> DerefMut for Savepoint<'_, '_, D> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.txn
+    }
+}
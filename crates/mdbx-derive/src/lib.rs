@@ -1,13 +1,47 @@
 pub use mdbx_derive_macros::*;
+#[cfg(feature = "mdbx")]
+#[doc(hidden)]
+pub use mdbx_derive_traits::__mdbx_database_struct;
+#[cfg(feature = "bcs")]
+pub use mdbx_derive_traits::codec::Bcs;
+#[cfg(any(feature = "serde_json", feature = "simd-json"))]
+pub use mdbx_derive_traits::codec::Json;
+pub use mdbx_derive_traits::codec::{
+    Fallback, LegacyCodecs, ObjectCodec, Postcard, Raw, SniffedObject, Zstd,
+};
 pub use mdbx_derive_traits::error::MDBXDeriveError as Error;
 pub use mdbx_derive_traits::key::{KeyObjectDecode, KeyObjectEncode};
-pub use mdbx_derive_traits::table::{TableObjectDecode, TableObjectEncode};
+pub use mdbx_derive_traits::table::{FixedSizeObject, TableObjectDecode, TableObjectEncode};
 #[cfg(feature = "mdbx")]
 pub use mdbx_derive_traits::{
-    orm::{HasMDBXDBIStore, HasMDBXEnvironment, HasMDBXTables, MDBXDatabase, MDBXTable},
-    {mdbx_database, mdbx_dupsort_table, mdbx_dupsort_table_def, mdbx_table, mdbx_table_def},
+    batch,
+    cursor::{TableCursor, TableItem},
+    dbi::{
+        CachedEnvironment, DatabaseEnv, DbiCache, ReadOnlyEnvironment, grow_map, open_env_read_only,
+    },
+    handle::{TableHandle, TxnTable},
+    migration::{self, Migration},
+    orm::{
+        DATABASE_METADATA_NAME, DbiTable, HasMDBXEnvironment, HasMDBXTableDbis, HasMDBXTables,
+        Here, MDBXDatabase, MDBXDupFixedTable, MDBXDupSortTable, MDBXTable, OptionalTableMode,
+        Owned, RetryPolicy, Shared, TABLE_METADATA_NAME, TableAccess, TableIndex, TableMode,
+        TableVisitor, There, WithFlags,
+    },
+    schema,
+    stat::TableStat,
+    txn::{DbTxn, Savepoint, WriteTxn, commit_tx},
+    {
+        mdbx_database, mdbx_dupfixed_table, mdbx_dupfixed_table_def, mdbx_dupsort_table,
+        mdbx_dupsort_table_def, mdbx_table, mdbx_table_def,
+    },
 };
 
+#[cfg(feature = "mdbx")]
+#[allow(deprecated)]
+pub use mdbx_derive_traits::orm::{HasMDBXDBIStore, MatchName};
+
+#[cfg(feature = "mdbx")]
+pub use futures;
 pub use paste;
 pub use tuple_list::{tuple_list, tuple_list_type};
 